@@ -1,36 +1,72 @@
-use ambi::string_match::{Automat, BoyerMoore, TextMatch, naive_match};
+use ambi::algorithm::{Algorithm, Approximate, MultiAlgorithm};
+use ambi::approximate::KMismatch;
+use ambi::metrics::hamming;
+use ambi::parallel::par_match;
+use ambi::string_match::{BoyerMoore, DenseAutomat};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 const GENOME: &str = include_str!("genome.in");
 const GENE: &str = include_str!("gene.in");
 
-pub fn criterion_bench_automaton(c: &mut Criterion) {
-    let pattern = GENE.chars().collect::<Vec<char>>();
-    let text = GENOME.chars().collect::<Vec<char>>();
-    let short_p = "TTATTAT".chars().collect::<Vec<char>>();
-    let auto_long = Automat::new(pattern.as_slice());
-    let auto_short = Automat::new(short_p.as_slice());
-    c.bench_function("Automaton - Long P", |b| b.iter(|| auto_long.match_text(black_box(text.as_slice()))));
-    c.bench_function("Automaton - Short P", |b| b.iter(|| auto_short.match_text(black_box(text.as_slice()))));
+pub fn criterion_bench_algorithms(c: &mut Criterion) {
+    let pattern_long = GENE.as_bytes().to_vec();
+    let text = GENOME.as_bytes().to_vec();
+    let pattern_short = b"TTATTAT".to_vec();
+    let sigma = b"ACGT";
+    for algorithm in Algorithm::ALL {
+        let long = algorithm.build(pattern_long.as_slice(), sigma);
+        let short = algorithm.build(pattern_short.as_slice(), sigma);
+        c.bench_function(&format!("{} - Long P", algorithm), |b| b.iter(|| long.match_text(black_box(text.as_slice()))));
+        c.bench_function(&format!("{} - Short P", algorithm), |b| b.iter(|| short.match_text(black_box(text.as_slice()))));
+    }
 }
 
-pub fn criterion_bench_naive(c: &mut Criterion) {
-    let pattern = GENE.chars().collect::<Vec<char>>();
-    let text = GENOME.chars().collect::<Vec<char>>();
-    let short_p = "TTATTAT".chars().collect::<Vec<char>>();
-    c.bench_function("Naive - Long P", |b| b.iter(|| naive_match(text.as_slice(), black_box(pattern.as_slice()))));
-    c.bench_function("Naive - Short P", |b| b.iter(|| naive_match(text.as_slice(), black_box(short_p.as_slice()))));
+pub fn criterion_bench_multi(c: &mut Criterion) {
+    let text = GENOME.as_bytes().to_vec();
+    let patterns: [&[u8]; 4] = [b"GAATTC", b"GGATCC", b"ATTTAAAT", GENE.as_bytes()];
+    for algorithm in MultiAlgorithm::ALL {
+        let matcher = algorithm.build(&patterns);
+        c.bench_function(&format!("{} - All P", algorithm), |b| b.iter(|| matcher.match_patterns(black_box(text.as_slice()))));
+    }
 }
 
-pub fn criterion_bench_boyer_moore(c: &mut Criterion) {
-    let pattern_long = GENE.chars().collect::<Vec<char>>();
-    let text = GENOME.chars().collect::<Vec<char>>();
-    let pattern_short = "TTATTAT".chars().collect::<Vec<char>>();
+/// Approximate matchers with one tolerated difference, those that can't take the long pattern skip it.
+pub fn criterion_bench_approximate(c: &mut Criterion) {
+    let text = GENOME.as_bytes().to_vec();
+    for algorithm in Approximate::ALL {
+        for (name, pattern) in [("Long P", GENE.as_bytes()), ("Short P", &b"TTANTAT"[..])] {
+            if let Some(matcher) = algorithm.build(pattern, 1) {
+                c.bench_function(&format!("{} - {}", algorithm, name), |b| b.iter(|| matcher.find_matches(black_box(text.as_slice()))));
+            }
+        }
+    }
+}
+
+pub fn criterion_bench_parallel(c: &mut Criterion) {
+    let pattern_long = GENE.as_bytes().to_vec();
+    let text = GENOME.as_bytes().to_vec();
     let bm_long = BoyerMoore::new(pattern_long.as_slice());
-    let bm_short = BoyerMoore::new(pattern_short.as_slice());
-    c.bench_function("Boyer Moore - Long P", |b| b.iter(|| bm_long.match_text(black_box(text.as_slice()))));
-    c.bench_function("Boyer Moore - Short P", |b| b.iter(|| bm_short.match_text(black_box(text.as_slice()))));
+    let dense_long = DenseAutomat::new(pattern_long.as_slice());
+    c.bench_function("Parallel Boyer Moore - Long P", |b| b.iter(|| par_match(&bm_long, black_box(text.as_slice()), 0)));
+    c.bench_function("Parallel Dense Automaton - Long P", |b| b.iter(|| par_match(&dense_long, black_box(text.as_slice()), 0)));
+}
+
+/// k-mismatch search against testing every window with the Hamming distance, on the genome
+/// and on a repetitive text where every window is compared in full.
+pub fn criterion_bench_k_mismatch(c: &mut Criterion) {
+    let genome = GENOME.chars().collect::<Vec<char>>();
+    let gene = GENE.chars().collect::<Vec<char>>();
+    let repetitive = vec!['A'; 200_000];
+    let run = vec!['A'; 1000];
+    for (name, text, pattern) in [("Long P", &genome, &gene), ("Repetitive", &repetitive, &run)] {
+        let m = pattern.len();
+        let km = KMismatch::new(pattern.as_slice(), 2);
+        c.bench_function(&format!("K-Mismatch - {}", name), |b| b.iter(|| km.match_mismatches(black_box(text.as_slice()))));
+        c.bench_function(&format!("Hamming per window - {}", name), |b| b.iter(|| {
+            (0..=text.len()-m).filter(|s| hamming(&text[*s..s+m], pattern.as_slice()).unwrap() <= 2).collect::<Vec<usize>>()
+        }));
+    }
 }
 
-criterion_group!(benches, criterion_bench_naive, criterion_bench_automaton, criterion_bench_boyer_moore);
+criterion_group!(benches, criterion_bench_algorithms, criterion_bench_multi, criterion_bench_approximate, criterion_bench_parallel, criterion_bench_k_mismatch);
 criterion_main!(benches);
@@ -1,6 +1,6 @@
-use std::usize;
+use std::collections::HashMap;
 
-use hashbrown::HashMap;
+use ambi::string_match::{Automat, TextMatch, naive_match};
 
 fn map_alpha(input: &[char]) -> HashMap<char, u8> {
     let mut alphabet = input.to_vec();
@@ -14,95 +14,6 @@ fn translate_with_alpha<T>(input: &[char], alpha: &HashMap<char, T>) -> Vec<T> w
     input.iter().map(|c| alpha[c]).collect()
 }
 
-fn str_match<T>(start: usize, text: &[T], pattern: &[T]) -> bool where T: Eq {
-    text[start..start+pattern.len()] == pattern[0..pattern.len()]
-}
-
-pub fn naive_match<T,P>(text: T, pattern: T) -> Vec<usize> where T: AsRef<[P]>, P: Eq {
-    let mut matches= vec![];
-    let text = text.as_ref();
-    let pattern = pattern.as_ref();
-    let t_count = text.len();
-    let p_count = pattern.len();
-    if p_count > t_count { panic!("pattern is longer than text"); }
-    for t_i in 0..t_count-p_count+1 {
-        if str_match(
-            t_i,
-            text,
-            pattern
-        ) {
-            matches.push(t_i);
-        }
-    }
-    matches
-}
-
-pub struct Automat{
-    accept: usize,
-    transition_functions: HashMap<usize, usize>,
-}
-
-impl Automat{
-    fn idx_char(idx: usize, c: &u8) -> usize {
-        (idx << 8) + *c as usize
-    }
-
-    pub fn new(pattern: &[u8]) -> Self{
-        let mut alphabet = pattern.to_vec();
-        alphabet.sort_unstable();
-        alphabet.dedup();
-        let mut transition_functions = HashMap::with_capacity(alphabet.len() * pattern.len()+1);
-        if pattern.len() > u16::MAX as usize { panic!("Pattern too long") }
-        for idx in 0..=pattern.len() {
-            for a in &alphabet {
-                if idx < pattern.len() && pattern[idx] == *a {
-                    transition_functions.insert(
-                        Automat::idx_char(idx, a),
-                        idx+1
-                    );
-                } else {
-                    let slice = [&pattern[0..idx], &[*a]].concat();
-                    let pos = Automat::sigma_suffix(slice.as_slice());
-                    if pos != 0 {
-                        transition_functions.insert(
-                            Automat::idx_char(idx, a),
-                            pos
-                        );
-                    }
-                }
-            }
-        }
-        Self{
-            accept: pattern.len(),
-            transition_functions
-        }
-    }
-
-    /// Return the length of the longest prefix that is also a suffix
-    fn sigma_suffix(x: &[u8]) -> usize{
-        for k in 1..x.len() {
-            let prefix = &x[0..x.len()-k];
-            let suffix = &x[k..x.len()];
-            if prefix == suffix {
-                return prefix.len();
-            }
-        }
-        0
-    }
-
-    pub fn match_text(&self, text: &[u8]) -> Vec<usize> {
-        let mut matches = vec![];
-        let mut start = 0;
-        for (idx, char) in text.iter().enumerate(){
-            start = *self.transition_functions.get(&Automat::idx_char(start, char)).unwrap_or(&0);
-            if start == self.accept {
-                matches.push(idx-self.accept+1);
-            }
-        }
-        matches
-    }
-}
-
 fn main() {
 
     let poem = r#"
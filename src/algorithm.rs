@@ -0,0 +1,342 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    approximate::{EditMatch, KDifference, KMismatch, Myers},
+    dna::{IupacAutomat, IupacPattern, NPolicy},
+    string_match::{AhoCorasick, Automat, BoyerMoore, ByteFinder, DenseAutomat, DenseSymbol, Horspool, KnuthMorrisPratt, Match, MatchSemantics, MultiMatch, MultiRabinKarp, Naive, RabinKarp, ShiftOr, Sunday, Symbol, TextMatch},
+    wildcard::Wildcard,
+    z_algorithm::ZMatcher
+};
+
+/// Every exact single pattern matcher of the crate, so callers can pick one by name.
+/// [`RealTimeKmp`](crate::string_match::RealTimeKmp) is left out, on a whole text it runs the dense automaton.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm{
+    Naive,
+    RabinKarp,
+    Automaton,
+    DenseAutomaton,
+    KnuthMorrisPratt,
+    BoyerMoore,
+    Horspool,
+    Sunday,
+    ShiftOr,
+    ZAlgorithm,
+    ByteFinder
+}
+
+impl Algorithm{
+    pub const ALL: [Algorithm; 11] = [
+        Algorithm::Naive,
+        Algorithm::RabinKarp,
+        Algorithm::Automaton,
+        Algorithm::DenseAutomaton,
+        Algorithm::KnuthMorrisPratt,
+        Algorithm::BoyerMoore,
+        Algorithm::Horspool,
+        Algorithm::Sunday,
+        Algorithm::ShiftOr,
+        Algorithm::ZAlgorithm,
+        Algorithm::ByteFinder
+    ];
+
+    /// Short name for command lines and config files, accepted by `from_str` next to the display name.
+    pub fn id(self) -> &'static str {
+        match self {
+            Algorithm::Naive => "naive",
+            Algorithm::RabinKarp => "rabin-karp",
+            Algorithm::Automaton => "automaton",
+            Algorithm::DenseAutomaton => "dense-automaton",
+            Algorithm::KnuthMorrisPratt => "kmp",
+            Algorithm::BoyerMoore => "boyer-moore",
+            Algorithm::Horspool => "horspool",
+            Algorithm::Sunday => "sunday",
+            Algorithm::ShiftOr => "shift-or",
+            Algorithm::ZAlgorithm => "z",
+            Algorithm::ByteFinder => "byte-finder"
+        }
+    }
+
+    /// Preprocess `pattern`, `sigma` is the alphabet of the text and only used by Rabin-Karp.
+    pub fn build<T>(self, pattern: &[T], sigma: &[T]) -> Box<dyn TextMatch<T> + Send + Sync> where T: DenseSymbol + Send + Sync + 'static {
+        match self {
+            Algorithm::Naive => Box::new(Naive::new(pattern)),
+            Algorithm::RabinKarp => Box::new(RabinKarp::new(pattern, sigma)),
+            Algorithm::Automaton => Box::new(Automat::new(pattern)),
+            Algorithm::DenseAutomaton => Box::new(DenseAutomat::new(pattern)),
+            Algorithm::KnuthMorrisPratt => Box::new(KnuthMorrisPratt::new(pattern)),
+            Algorithm::BoyerMoore => Box::new(BoyerMoore::new(pattern)),
+            Algorithm::Horspool => Box::new(Horspool::new(pattern)),
+            Algorithm::Sunday => Box::new(Sunday::new(pattern)),
+            Algorithm::ShiftOr => Box::new(ShiftOr::new(pattern)),
+            Algorithm::ZAlgorithm => Box::new(ZMatcher::new(pattern)),
+            Algorithm::ByteFinder => Box::new(ByteFinder::new(pattern))
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Algorithm::Naive => "Naive",
+            Algorithm::RabinKarp => "Rabin Karp",
+            Algorithm::Automaton => "Automaton",
+            Algorithm::DenseAutomaton => "Automaton (dense)",
+            Algorithm::KnuthMorrisPratt => "Knuth Morris Pratt",
+            Algorithm::BoyerMoore => "Boyer Moore",
+            Algorithm::Horspool => "Horspool",
+            Algorithm::Sunday => "Sunday",
+            Algorithm::ShiftOr => "Shift Or",
+            Algorithm::ZAlgorithm => "Z Algorithm",
+            Algorithm::ByteFinder => "Byte Finder (SIMD)"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    /// Either the [`Algorithm::id`] or the display name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL.iter().copied()
+            .find(|a| a.id().eq_ignore_ascii_case(s) || a.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown algorithm '{}'", s))
+    }
+}
+
+/// Every multi pattern matcher of the crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MultiAlgorithm{
+    AhoCorasick,
+    RabinKarp
+}
+
+impl MultiAlgorithm{
+    pub const ALL: [MultiAlgorithm; 2] = [
+        MultiAlgorithm::AhoCorasick,
+        MultiAlgorithm::RabinKarp
+    ];
+
+    /// Short name for command lines and config files, accepted by `from_str` next to the display name.
+    pub fn id(self) -> &'static str {
+        match self {
+            MultiAlgorithm::AhoCorasick => "aho-corasick",
+            MultiAlgorithm::RabinKarp => "multi-rabin-karp"
+        }
+    }
+
+    /// Preprocess all `patterns` for a single pass over the text.
+    pub fn build<T, P>(self, patterns: &[P]) -> Box<dyn MultiMatch<T> + Send + Sync> where T: Symbol + Send + Sync + 'static, P: AsRef<[T]> {
+        match self {
+            MultiAlgorithm::AhoCorasick => Box::new(AhoCorasick::new(patterns)),
+            MultiAlgorithm::RabinKarp => Box::new(MultiRabinKarp::new(patterns))
+        }
+    }
+}
+
+impl fmt::Display for MultiAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MultiAlgorithm::AhoCorasick => "Aho Corasick",
+            MultiAlgorithm::RabinKarp => "Rabin Karp (multi pattern)"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MultiAlgorithm {
+    type Err = String;
+
+    /// Either the [`MultiAlgorithm::id`] or the display name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MultiAlgorithm::ALL.iter().copied()
+            .find(|a| a.id().eq_ignore_ascii_case(s) || a.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown algorithm '{}'", s))
+    }
+}
+
+/// Matcher whose occurrences may differ from the pattern, reported as scored [`Match`]es
+/// whose length may differ from the pattern's as well.
+pub trait ApproximateMatch{
+    fn find_matches(&self, text: &[u8]) -> Vec<Match>;
+
+    /// The matches `semantics` selects, sorted by start.
+    fn find_matches_with(&self, text: &[u8], semantics: MatchSemantics) -> Vec<Match> {
+        semantics.select_matches(self.find_matches(text))
+    }
+}
+
+impl ApproximateMatch for Wildcard<u8> {
+    fn find_matches(&self, text: &[u8]) -> Vec<Match> {
+        TextMatch::find_matches(self, text)
+    }
+}
+
+impl ApproximateMatch for IupacPattern {
+    fn find_matches(&self, text: &[u8]) -> Vec<Match> {
+        TextMatch::find_matches(self, text)
+    }
+}
+
+impl ApproximateMatch for IupacAutomat {
+    fn find_matches(&self, text: &[u8]) -> Vec<Match> {
+        TextMatch::find_matches(self, text)
+    }
+}
+
+impl ApproximateMatch for KMismatch<u8> {
+    fn find_matches(&self, text: &[u8]) -> Vec<Match> {
+        TextMatch::find_matches(self, text)
+    }
+}
+
+impl ApproximateMatch for KDifference<u8> {
+    fn find_matches(&self, text: &[u8]) -> Vec<Match> {
+        EditMatch::find_matches(self, text)
+    }
+}
+
+impl ApproximateMatch for Myers<u8> {
+    fn find_matches(&self, text: &[u8]) -> Vec<Match> {
+        EditMatch::find_matches(self, text)
+    }
+}
+
+/// Every matcher of the crate tolerating differences to the pattern, over byte texts like DNA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Approximate{
+    Wildcard,
+    Iupac,
+    IupacAutomaton,
+    KMismatch,
+    KDifference,
+    Myers
+}
+
+impl Approximate{
+    pub const ALL: [Approximate; 6] = [
+        Approximate::Wildcard,
+        Approximate::Iupac,
+        Approximate::IupacAutomaton,
+        Approximate::KMismatch,
+        Approximate::KDifference,
+        Approximate::Myers
+    ];
+
+    /// Short name for command lines and config files, accepted by `from_str` next to the display name.
+    pub fn id(self) -> &'static str {
+        match self {
+            Approximate::Wildcard => "wildcard",
+            Approximate::Iupac => "iupac",
+            Approximate::IupacAutomaton => "iupac-automaton",
+            Approximate::KMismatch => "k-mismatch",
+            Approximate::KDifference => "k-difference",
+            Approximate::Myers => "myers"
+        }
+    }
+
+    /// Whether the search allows `k` mismatches or edits, the others ignore it.
+    pub fn uses_k(self) -> bool {
+        matches!(self, Approximate::KMismatch | Approximate::KDifference | Approximate::Myers)
+    }
+
+    /// Preprocess `pattern` allowing `k` mismatches or edits. `N` matches any symbol in
+    /// wildcard patterns, IUPAC patterns take their codes literally and degenerate text
+    /// positions never match them. None if the IUPAC matchers can't take the pattern.
+    pub fn build(self, pattern: &[u8], k: usize) -> Option<Box<dyn ApproximateMatch + Send + Sync>> {
+        Some(match self {
+            Approximate::Wildcard => Box::new(Wildcard::new(pattern, b'N', false)),
+            Approximate::Iupac => Box::new(IupacPattern::new(pattern, NPolicy::Mismatch)?),
+            Approximate::IupacAutomaton => Box::new(IupacAutomat::new(&IupacPattern::new(pattern, NPolicy::Mismatch)?)?),
+            Approximate::KMismatch => Box::new(KMismatch::new(pattern, k)),
+            Approximate::KDifference => Box::new(KDifference::new(pattern, k)),
+            Approximate::Myers => Box::new(Myers::new(pattern, k))
+        })
+    }
+}
+
+impl fmt::Display for Approximate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Approximate::Wildcard => "Wildcard (FFT)",
+            Approximate::Iupac => "IUPAC",
+            Approximate::IupacAutomaton => "IUPAC (automaton)",
+            Approximate::KMismatch => "K Mismatch",
+            Approximate::KDifference => "K Difference",
+            Approximate::Myers => "Myers"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Approximate {
+    type Err = String;
+
+    /// Either the [`Approximate::id`] or the display name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Approximate::ALL.iter().copied()
+            .find(|a| a.id().eq_ignore_ascii_case(s) || a.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown algorithm '{}'", s))
+    }
+}
+
+#[test]
+fn test_algorithms() {
+    use crate::string_match::naive_match;
+    let text = b"TTATTATGAATTCTTATTATTAT";
+    let sigma = b"ACGT";
+    for pattern in [&b"TTATTAT"[..], b"GAATTC", b"T", b"CCC"] {
+        let expected = naive_match(&text[..], pattern);
+        for algorithm in Algorithm::ALL {
+            assert_eq!(expected, algorithm.build(pattern, sigma).match_text(text), "{}", algorithm);
+        }
+    }
+    // An empty pattern matches nowhere and neither does one longer than the text
+    let longer = [&text[..], b"A"].concat();
+    for pattern in [&b""[..], longer.as_slice()] {
+        for algorithm in Algorithm::ALL {
+            assert!(algorithm.build(pattern, sigma).match_text(text).is_empty(), "{} with {} symbols", algorithm, pattern.len());
+        }
+    }
+}
+
+#[test]
+fn test_algorithm_names() {
+    for algorithm in Algorithm::ALL {
+        assert_eq!(Ok(algorithm), algorithm.to_string().parse());
+        assert_eq!(Ok(algorithm), algorithm.id().to_uppercase().parse());
+    }
+    assert!("quick".parse::<Algorithm>().is_err());
+}
+
+#[test]
+fn test_multi_algorithms() {
+    let text = b"GAATTCGGATCCATTTAAATGAATTCAATTGGATCCC";
+    let patterns: [&[u8]; 4] = [b"GAATTC", b"", b"AATT", b"C"];
+    let expected = AhoCorasick::new(&patterns).match_patterns(text);
+    for algorithm in MultiAlgorithm::ALL {
+        assert_eq!(expected, algorithm.build(&patterns).match_patterns(text), "{}", algorithm);
+        assert_eq!(Ok(algorithm), algorithm.to_string().parse());
+        assert_eq!(Ok(algorithm), algorithm.id().parse());
+    }
+}
+
+#[test]
+fn test_approximate_algorithms() {
+    let text = b"GGATTACAGGATACAGGNATTACA";
+    for algorithm in Approximate::ALL {
+        let exact = algorithm.build(b"ATTACA", 0).unwrap().find_matches(text);
+        assert_eq!(vec![2, 18], exact.iter().map(|m| m.start).collect::<Vec<usize>>(), "{}", algorithm);
+        assert_eq!(Ok(algorithm), algorithm.to_string().parse());
+        assert_eq!(Ok(algorithm), algorithm.id().parse());
+    }
+    // A wildcard or degenerate position, one tolerated difference or edit each
+    let matches = [("ATNACA", 0), ("ATNACA", 0), ("ATNACA", 0), ("ATTACA", 1), ("ATTACA", 1), ("ATTACA", 1)];
+    for (algorithm, (pattern, k)) in Approximate::ALL.iter().zip(matches) {
+        let found = algorithm.build(pattern.as_bytes(), k).unwrap().find_matches_with(text, MatchSemantics::NonOverlapping);
+        assert!(found.iter().any(|m| m.start == 2) && found.iter().any(|m| m.start == 18), "{}", algorithm);
+    }
+    assert!(Approximate::Iupac.build(b"ATXACA", 0).is_none());
+    assert!(Approximate::IupacAutomaton.build(&[b'A'; 65], 0).is_none());
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use crate::{string_match::{Match, MatchSemantics, Symbol, TextMatch}, suffix::SuffixArray};
+
+/// Symbols an extension compares directly before it jumps with the suffix array.
+const DIRECT: usize = 16;
+/// Direct comparisons per text symbol the k-mismatch search makes before it builds the suffix array.
+const BUDGET: usize = 32;
+
+/// Finds all windows of a text within Hamming distance `k` of the pattern.
+/// Every alignment is checked by at most k+1 kangaroo jumps (Landau-Vishkin).
+/// Extensions compare symbols directly, which is fastest on most texts, and once
+/// that has cost about as much as building it, long extensions become constant time
+/// longest common extension queries on one suffix array of the pattern and the text.
+pub struct KMismatch<T>{
+    pattern: Vec<T>,
+    k: usize
+}
+
+impl<T> KMismatch<T> where T: Symbol + Ord {
+    pub fn new(pattern: &[T], k: usize) -> Self {
+        Self{
+            pattern: pattern.to_vec(),
+            k
+        }
+    }
+
+    /// Suffix array of the pattern, a separator that can't be extended over and the text.
+    fn index(&self, text: &[T]) -> SuffixArray {
+        let mut combined: Vec<Option<T>> = self.pattern.iter().map(|c| Some(*c)).collect();
+        combined.push(None);
+        combined.extend(text.iter().map(|c| Some(*c)));
+        SuffixArray::new(combined.as_slice())
+    }
+
+    /// Mismatches of the alignment at `s`, as long as there are at most `k` of them.
+    /// Symbols are compared directly until `budget` is spent, then the suffix array is built.
+    fn kangaroo(&self, text: &[T], s: usize, index: &mut Option<SuffixArray>, budget: &mut usize) -> Option<usize> {
+        let m = self.pattern.len();
+        let window = &text[s..s+m];
+        let mut mismatches = 0;
+        if *budget > 0 {
+            for (i, (p, t)) in self.pattern.iter().zip(window).enumerate() {
+                if p != t {
+                    mismatches += 1;
+                    if mismatches > self.k {
+                        *budget = budget.saturating_sub(i+1);
+                        return None;
+                    }
+                }
+            }
+            *budget = budget.saturating_sub(m);
+            return Some(mismatches);
+        }
+        let mut i = 0;
+        loop {
+            // A short direct extension, a long one jumps
+            let start = i;
+            while i < m && self.pattern[i] == window[i] {
+                i += 1;
+                if i-start == DIRECT {
+                    let sa = index.get_or_insert_with(|| self.index(text));
+                    i += sa.lce(i, m+1+s+i);
+                    break;
+                }
+            }
+            if i >= m {
+                return Some(mismatches);
+            }
+            mismatches += 1;
+            if mismatches > self.k {
+                return None;
+            }
+            i += 1;
+        }
+    }
+
+    /// Lazily yield the positions with at most `k` mismatches together with their mismatch count.
+    pub fn mismatches<'a>(&'a self, text: &'a [T]) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let (m, n) = (self.pattern.len(), text.len());
+        let windows = if m == 0 || m > n { 0 } else { n+1-m };
+        let mut index = None;
+        let mut budget = BUDGET*(n+m);
+        (0..windows).filter_map(move |s| self.kangaroo(text, s, &mut index, &mut budget).map(|d| (s, d)))
+    }
+
+    /// All positions with at most `k` mismatches together with their mismatch count.
+    pub fn match_mismatches(&self, text: &[T]) -> Vec<(usize, usize)> {
+        self.mismatches(text).collect()
+    }
+}
+
+impl<T> TextMatch<T> for KMismatch<T> where T: Symbol + Ord {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        Box::new(self.mismatches(text).map(|(i, _)| i))
+    }
+
+    /// Matches scored by their number of mismatches.
+    fn find_matches(&self, text: &[T]) -> Vec<Match> {
+        let m = self.pattern.len();
+        self.mismatches(text).map(|(s, d)| Match::new(s, s+m).with_score(d)).collect()
+    }
+}
+
+#[test]
+fn test_k_mismatch() {
+    let text = "ACGTTGACGATTGACCAGTA".chars().collect::<Vec<char>>();
+    let pattern = "TGAC".chars().collect::<Vec<char>>();
+    let km = KMismatch::new(pattern.as_slice(), 1);
+    assert_eq!(vec![(4, 0), (11, 0)], km.match_mismatches(text.as_slice()));
+    let km = KMismatch::new(pattern.as_slice(), 2);
+    let expected = (0..=text.len()-pattern.len()).filter_map(|s| {
+        crate::metrics::hamming(&text[s..s+pattern.len()], pattern.as_slice()).filter(|d| *d <= 2).map(|d| (s, d))
+    }).collect::<Vec<(usize, usize)>>();
+    assert_eq!(expected, km.match_mismatches(text.as_slice()));
+    assert_eq!(Some(Match::new(4, 8).with_score(0)), km.find_matches(text.as_slice()).into_iter().find(|m| m.start == 4));
+    assert_eq!(expected.first().map(|(s, _)| *s), km.find_first(text.as_slice()));
+}
+
+#[test]
+fn test_k_mismatch_repetitive() {
+    // Nearly every window matches, so the direct comparisons run out of budget and the suffix array takes over
+    let mut text = vec!['A'; 3000];
+    for i in (0..text.len()).step_by(97) {
+        text[i] = 'C';
+    }
+    let mut pattern = vec!['A'; 150];
+    pattern[40] = 'C';
+    let km = KMismatch::new(pattern.as_slice(), 2);
+    let expected = (0..=text.len()-pattern.len()).filter_map(|s| {
+        crate::metrics::hamming(&text[s..s+pattern.len()], pattern.as_slice()).filter(|d| *d <= 2).map(|d| (s, d))
+    }).collect::<Vec<(usize, usize)>>();
+    assert_eq!(expected, km.match_mismatches(text.as_slice()));
+    assert!(KMismatch::new(&pattern[..0], 2).match_mismatches(text.as_slice()).is_empty());
+}
+
+/// Start of the shortest substring ending at `end` that is `distance` edits away from the pattern.
+/// The DP runs backwards from `end` with the reversed pattern, so it is bounded by m+k symbols.
+fn start_position<T>(text: &[T], pattern: &[T], end: usize, distance: usize) -> usize where T: Eq {
+    let m = pattern.len();
+    // column[i] = edits between the last i symbols of the pattern and the text read so far
+    let mut column: Vec<usize> = (0..=m).collect();
+    if column[m] == distance {
+        return end;
+    }
+    for (read, t) in text[..end].iter().rev().enumerate() {
+        let mut diagonal = column[0];
+        column[0] += 1;
+        for i in 1..=m {
+            let next = (diagonal + (pattern[m-i] != *t) as usize).min(column[i]+1).min(column[i-1]+1);
+            diagonal = column[i];
+            column[i] = next;
+        }
+        if column[m] == distance {
+            return end-read-1;
+        }
+    }
+    0
+}
+
+/// Approximate matcher under edit distance. Implementors find where the occurrences end,
+/// their spans and scored matches are derived from that.
+pub trait EditMatch<T> where T: Symbol {
+    /// The pattern occurrences are compared to.
+    fn pattern(&self) -> &[T];
+
+    /// `(end, distance)` of every text position at which a substring within `k` edits of the pattern ends,
+    /// `end` is exclusive.
+    fn match_ends(&self, text: &[T]) -> Vec<(usize, usize)>;
+
+    /// Like [`EditMatch::match_ends`] but as `(start, end, distance)`, taking the shortest occurrence per end.
+    fn match_spans(&self, text: &[T]) -> Vec<(usize, usize, usize)> {
+        self.match_ends(text).into_iter().map(|(end, d)| (start_position(text, self.pattern(), end, d), end, d)).collect()
+    }
+
+    /// [`EditMatch::match_spans`] as matches scored by their edit distance.
+    fn find_matches(&self, text: &[T]) -> Vec<Match> {
+        self.match_spans(text).into_iter().map(|(start, end, d)| Match::new(start, end).with_score(d)).collect()
+    }
+
+    /// The matches `semantics` selects, sorted by start. Among spans starting at the same
+    /// position the shortest is taken, or the longest under `LeftmostLongest`.
+    fn find_matches_with(&self, text: &[T], semantics: MatchSemantics) -> Vec<Match> {
+        semantics.select_matches(self.find_matches(text))
+    }
+}
+
+/// Approximate matching under edit distance with Ukkonen's cut-off dynamic programming,
+/// only the rows of each DP column that can still be within `k` are computed.
+pub struct KDifference<T>{
+    pattern: Vec<T>,
+    k: usize
+}
+
+impl<T> KDifference<T> where T: Symbol {
+    pub fn new(pattern: &[T], k: usize) -> Self {
+        Self{
+            pattern: pattern.to_vec(),
+            k
+        }
+    }
+
+}
+
+impl<T> EditMatch<T> for KDifference<T> where T: Symbol {
+    fn pattern(&self) -> &[T] {
+        &self.pattern
+    }
+
+    fn match_ends(&self, text: &[T]) -> Vec<(usize, usize)> {
+        let mut matches = vec![];
+        let (m, k) = (self.pattern.len(), self.k);
+        let mut column: Vec<usize> = (0..=m).collect();
+        // Last active row, all rows below it exceed k
+        let mut lact = (k+1).min(m);
+        if m <= k {
+            matches.push((0, m));
+        }
+        for (j, t) in text.iter().enumerate() {
+            let mut diagonal = 0;
+            for i in 1..=lact {
+                let next = (diagonal + (self.pattern[i-1] != *t) as usize).min(column[i]+1).min(column[i-1]+1);
+                diagonal = column[i];
+                column[i] = next;
+            }
+            while column[lact] > k {
+                lact -= 1;
+            }
+            if lact == m {
+                matches.push((j+1, column[m]));
+            } else {
+                lact += 1;
+            }
+        }
+        matches
+    }
+}
+
+/// Approximate matching under edit distance with Myers' bit-vector algorithm,
+/// a DP column is updated with a handful of word operations per 64 pattern symbols.
+/// Longer patterns are split into blocks of 64 rows that pass the horizontal
+/// difference at their last row on to the next block (Hyyrö).
+pub struct Myers<T>{
+    /// Bit `i % 64` of word `i / 64` is set where the pattern has the symbol
+    peq: HashMap<T, Vec<u64>>,
+    pattern: Vec<T>,
+    k: usize
+}
+
+impl<T> Myers<T> where T: Symbol {
+    pub fn new(pattern: &[T], k: usize) -> Self {
+        let words = pattern.len().div_ceil(64);
+        let mut peq = HashMap::new();
+        for (i, c) in pattern.iter().enumerate() {
+            peq.entry(*c).or_insert_with(|| vec![0u64; words])[i/64] |= 1 << (i%64);
+        }
+        Self{
+            peq,
+            pattern: pattern.to_vec(),
+            k
+        }
+    }
+}
+
+impl<T> EditMatch<T> for Myers<T> where T: Symbol {
+    fn pattern(&self) -> &[T] {
+        &self.pattern
+    }
+
+    fn match_ends(&self, text: &[T]) -> Vec<(usize, usize)> {
+        let mut matches = vec![];
+        let m = self.pattern.len();
+        if m <= self.k {
+            matches.push((0, m));
+        }
+        if m == 0 {
+            matches.extend((1..=text.len()).map(|j| (j, 0)));
+            return matches;
+        }
+        let words = m.div_ceil(64);
+        let none = vec![0; words];
+        // The score is tracked at row m, in the last block
+        let high = 1 << ((m-1)%64);
+        let (mut pv, mut mv, mut score) = (vec![!0u64; words], vec![0u64; words], m);
+        for (j, t) in text.iter().enumerate() {
+            let eq = self.peq.get(t).unwrap_or(&none);
+            // Horizontal difference entering the block from above, the top row is all zeros
+            let mut h: isize = 0;
+            for w in 0..words {
+                let (neg, pos) = ((h < 0) as u64, (h > 0) as u64);
+                let xv = eq[w] | mv[w];
+                let e = eq[w] | neg;
+                let xh = ((e & pv[w]).wrapping_add(pv[w]) ^ pv[w]) | e;
+                let mut ph = mv[w] | !(xh | pv[w]);
+                let mut mh = pv[w] & xh;
+                let last = if w+1 == words { high } else { 1 << 63 };
+                h = if ph & last != 0 { 1 } else if mh & last != 0 { -1 } else { 0 };
+                ph = (ph << 1) | pos;
+                mh = (mh << 1) | neg;
+                pv[w] = mh | !(xv | ph);
+                mv[w] = ph & xv;
+            }
+            score = (score as isize + h) as usize;
+            if score <= self.k {
+                matches.push((j+1, score));
+            }
+        }
+        matches
+    }
+}
+
+#[test]
+fn test_k_difference() {
+    let text = "ACGTTGACGATTGACCAGTAGGACA".chars().collect::<Vec<char>>();
+    let pattern = "TGACC".chars().collect::<Vec<char>>();
+    // Full DP with a free start in the text as oracle
+    let mut column: Vec<usize> = (0..=pattern.len()).collect();
+    let mut expected = vec![];
+    for (j, t) in text.iter().enumerate() {
+        let mut next = vec![0];
+        for i in 1..=pattern.len() {
+            next.push((column[i-1] + (pattern[i-1] != *t) as usize).min(column[i]+1).min(next[i-1]+1));
+        }
+        column = next;
+        if column[pattern.len()] <= 2 {
+            expected.push((j+1, column[pattern.len()]));
+        }
+    }
+    assert_eq!(expected, KDifference::new(pattern.as_slice(), 2).match_ends(text.as_slice()));
+    assert_eq!(expected, Myers::new(pattern.as_slice(), 2).match_ends(text.as_slice()));
+}
+
+#[test]
+fn test_k_difference_spans() {
+    let text = b"GGATTACAGGATACAGG";
+    let spans = KDifference::new(b"ATTACA", 1).match_spans(text);
+    assert_eq!(spans, Myers::new(b"ATTACA", 1).match_spans(text));
+    assert!(spans.contains(&(2, 8, 0)));
+    assert!(spans.contains(&(10, 15, 1)));
+    assert!(Myers::new(b"ATTACA", 1).find_matches(text).contains(&Match::new(10, 15).with_score(1)));
+    // Several spans end around each occurrence, only one of them per occurrence is kept
+    let disjoint = Myers::new(b"ATTACA", 1).find_matches_with(text, MatchSemantics::NonOverlapping);
+    assert_eq!(vec![Match::new(2, 7).with_score(1), Match::new(10, 15).with_score(1)], disjoint);
+    let longest = KDifference::new(b"ATTACA", 1).find_matches_with(text, MatchSemantics::LeftmostLongest);
+    assert_eq!(vec![2, 10], longest.iter().map(|m| m.start).collect::<Vec<usize>>());
+    assert!(longest[0].end > 7);
+}
+
+#[test]
+fn test_myers_blocks() {
+    // Patterns over 64 symbols span several words, which pass the horizontal differences on
+    let text: Vec<u8> = (0..600u32).map(|i| b"ACGT"[(i*i % 11 % 4) as usize]).collect();
+    let mut pattern = text[200..350].to_vec();
+    pattern[70] ^= 0x20;
+    pattern.remove(100);
+    for k in [0, 2, 5] {
+        assert_eq!(KDifference::new(pattern.as_slice(), k).match_ends(text.as_slice()), Myers::new(pattern.as_slice(), k).match_ends(text.as_slice()));
+    }
+    assert!(Myers::new(pattern.as_slice(), 2).find_matches(text.as_slice()).contains(&Match::new(200, 350).with_score(2)));
+}
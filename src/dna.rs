@@ -0,0 +1,420 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::string_match::{Match, MatchSemantics, ShiftOr, Symbol, TextMatch};
+pub use crate::string_match::Strand;
+
+/// Nucleotide symbols that know their Watson-Crick partner.
+pub trait Nucleotide: Symbol {
+    fn complement(self) -> Self;
+}
+
+impl Nucleotide for u8 {
+    /// Complement of a nucleotide or IUPAC code, anything else stays as it is.
+    fn complement(self) -> Self {
+        let upper = match self.to_ascii_uppercase() {
+            b'A' => b'T',
+            b'T' | b'U' => b'A',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'R' => b'Y',
+            b'Y' => b'R',
+            b'K' => b'M',
+            b'M' => b'K',
+            b'B' => b'V',
+            b'V' => b'B',
+            b'D' => b'H',
+            b'H' => b'D',
+            _ => return self
+        };
+        if self.is_ascii_lowercase() { upper.to_ascii_lowercase() } else { upper }
+    }
+}
+
+impl Nucleotide for char {
+    fn complement(self) -> Self {
+        if self.is_ascii() { (self as u8).complement() as char } else { self }
+    }
+}
+
+pub fn reverse_complement<T>(sequence: &[T]) -> Vec<T> where T: Nucleotide {
+    sequence.iter().rev().map(|n| n.complement()).collect()
+}
+
+/// Searches a pattern on both strands of a DNA text by also matching its reverse complement.
+/// Palindromic patterns like `GAATTC` equal their reverse complement and are only searched once.
+pub struct StrandMatch<M>{
+    forward: M,
+    reverse: Option<M>
+}
+
+impl<M> StrandMatch<M>{
+    pub fn new<T, F>(pattern: &[T], build: F) -> Self where T: Nucleotide, F: Fn(&[T]) -> M {
+        let reverse = reverse_complement(pattern);
+        Self{
+            forward: build(pattern),
+            reverse: if reverse.as_slice() == pattern { None } else { Some(build(reverse.as_slice())) }
+        }
+    }
+
+    /// Search the forward strand only, e.g. for single stranded sequences.
+    pub fn forward(matcher: M) -> Self {
+        Self{
+            forward: matcher,
+            reverse: None
+        }
+    }
+
+    /// All hits sorted by position, a reverse hit at `i` means the reverse complement
+    /// of the pattern occupies the same forward coordinates as a forward hit at `i` would.
+    pub fn match_strands<T>(&self, text: &[T]) -> Vec<(usize, Strand)> where M: TextMatch<T> {
+        let mut matches: Vec<(usize, Strand)> = self.forward.find_iter(text).map(|i| (i, Strand::Forward)).collect();
+        if let Some(reverse) = &self.reverse {
+            matches.extend(reverse.find_iter(text).map(|i| (i, Strand::Reverse)));
+            matches.sort_unstable();
+        }
+        matches
+    }
+
+    /// All hits with their spans and strands, sorted by position.
+    pub fn find_matches<T>(&self, text: &[T]) -> Vec<Match> where M: TextMatch<T> {
+        let mut matches = self.forward.find_matches(text);
+        if let Some(reverse) = &self.reverse {
+            matches.extend(reverse.find_matches(text).into_iter().map(|m| m.with_strand(Strand::Reverse)));
+            matches.sort_unstable();
+        }
+        matches
+    }
+
+    /// Like [`StrandMatch::match_strands`], with the hits of both strands selected together by `semantics`.
+    pub fn match_strands_with<T>(&self, text: &[T], semantics: MatchSemantics) -> Vec<(usize, Strand)> where M: TextMatch<T> {
+        self.find_matches_with(text, semantics).into_iter().map(|m| (m.start, m.strand)).collect()
+    }
+
+    /// Like [`StrandMatch::find_matches`], with the hits of both strands selected together by `semantics`.
+    /// A forward hit wins over a reverse one at the same position.
+    pub fn find_matches_with<T>(&self, text: &[T], semantics: MatchSemantics) -> Vec<Match> where M: TextMatch<T> {
+        semantics.select_matches(self.find_matches(text))
+    }
+}
+
+impl<M> StrandMatch<Option<M>>{
+    /// The search on both strands if the matcher for each of them could be built.
+    pub fn transpose(self) -> Option<StrandMatch<M>> {
+        Some(StrandMatch{
+            forward: self.forward?,
+            reverse: match self.reverse {
+                Some(reverse) => Some(reverse?),
+                None => None
+            }
+        })
+    }
+}
+
+/// A set of patterns extended by the reverse complements of those that aren't palindromic,
+/// so a single multi pattern search covers both strands.
+pub struct StrandPatterns<T>{
+    patterns: Vec<Vec<T>>,
+    /// Id in the original set and strand of every pattern
+    origins: Vec<(usize, Strand)>
+}
+
+impl<T> StrandPatterns<T> where T: Nucleotide {
+    pub fn new<P>(patterns: &[P]) -> Self where P: AsRef<[T]> {
+        let mut strands = Self::forward(patterns);
+        for (id, pattern) in patterns.iter().enumerate() {
+            let reverse = reverse_complement(pattern.as_ref());
+            if reverse.as_slice() != pattern.as_ref() {
+                strands.patterns.push(reverse);
+                strands.origins.push((id, Strand::Reverse));
+            }
+        }
+        strands
+    }
+
+    /// The patterns as they are, e.g. for single stranded sequences.
+    pub fn forward<P>(patterns: &[P]) -> Self where P: AsRef<[T]> {
+        Self{
+            patterns: patterns.iter().map(|p| p.as_ref().to_vec()).collect(),
+            origins: (0..patterns.len()).map(|id| (id, Strand::Forward)).collect()
+        }
+    }
+
+    /// The patterns to search for, reverse complements last.
+    pub fn patterns(&self) -> &[Vec<T>] {
+        &self.patterns
+    }
+
+    /// Matches of the patterns to search for as matches of the original ones, tagged with
+    /// their strand and sorted by position.
+    pub fn tag(&self, matches: Vec<Match>) -> Vec<Match> {
+        let mut matches: Vec<Match> = matches.into_iter().map(|m| {
+            let (id, strand) = self.origins[m.pattern];
+            m.with_pattern(id).with_strand(strand)
+        }).collect();
+        matches.sort_unstable();
+        matches
+    }
+}
+
+const A: u8 = 1;
+const C: u8 = 2;
+const G: u8 = 4;
+const T: u8 = 8;
+
+/// Nucleotides an IUPAC code stands for, as a bit set over A, C, G and T.
+pub fn iupac_class(code: u8) -> Option<u8> {
+    Some(match code.to_ascii_uppercase() {
+        b'A' => A,
+        b'C' => C,
+        b'G' => G,
+        b'T' | b'U' => T,
+        b'R' => A | G,
+        b'Y' => C | T,
+        b'S' => G | C,
+        b'W' => A | T,
+        b'K' => G | T,
+        b'M' => A | C,
+        b'B' => C | G | T,
+        b'D' => A | G | T,
+        b'H' => A | C | T,
+        b'V' => A | C | G,
+        b'N' => A | C | G | T,
+        _ => return None
+    })
+}
+
+/// How an `N`, or any other degenerate code, in the text is compared to the pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NPolicy{
+    /// Degenerate text positions never match
+    Mismatch,
+    /// Degenerate text positions match if the pattern position allows all of their bases, e.g. an `N` only matches an `N`
+    Strict,
+    /// Degenerate text positions match if they share a base with the pattern position, e.g. an `N` matches everything
+    Match
+}
+
+impl NPolicy{
+    fn accepts(self, pattern: u8, text: u8) -> bool {
+        match self {
+            _ if text == 0 => false,
+            NPolicy::Mismatch => text.count_ones() == 1 && pattern & text != 0,
+            NPolicy::Strict => text & !pattern == 0,
+            NPolicy::Match => pattern & text != 0
+        }
+    }
+}
+
+/// Nucleotide pattern whose positions are IUPAC classes, e.g. `GGNCC` or `RAATTY`.
+/// Matching it directly as a [`TextMatch`] compares every window naively.
+pub struct IupacPattern{
+    classes: Vec<u8>,
+    policy: NPolicy
+}
+
+impl IupacPattern{
+    /// `None` if the pattern contains a symbol that is no IUPAC code.
+    pub fn new(pattern: &[u8], policy: NPolicy) -> Option<Self> {
+        Some(Self{
+            classes: pattern.iter().map(|c| iupac_class(*c)).collect::<Option<Vec<u8>>>()?,
+            policy
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Whether position `idx` of the pattern matches the text symbol.
+    pub fn accepts(&self, idx: usize, symbol: u8) -> bool {
+        self.policy.accepts(self.classes[idx], iupac_class(symbol).unwrap_or(0))
+    }
+
+    /// Bit-parallel matcher for the pattern, any length is fine.
+    pub fn shift_or(&self) -> ShiftOr<u8> {
+        ShiftOr::from_classes(self.len(), 0..=u8::MAX, |i, b| self.accepts(i, b))
+    }
+
+    /// Bit `i` of entry `b` is set if position `i` accepts the byte `b`, for up to 64 positions.
+    fn masks(&self) -> Vec<u64> {
+        (0..=u8::MAX).map(|b| {
+            (0..self.len()).filter(|i| self.accepts(*i, b)).fold(0, |mask, i| mask | 1 << i)
+        }).collect()
+    }
+}
+
+impl TextMatch<u8> for IupacPattern {
+    fn pattern_len(&self) -> usize {
+        self.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [u8]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let m = self.len();
+        Box::new((0..(text.len()+1).saturating_sub(m)).filter(move |s| {
+            m > 0 && (0..m).all(|i| self.accepts(i, text[s+i]))
+        }))
+    }
+}
+
+/// Deterministic automaton for IUPAC patterns. Since a text symbol may match
+/// several classes, a state is the set of pattern prefixes matched so far
+/// (the Shift-And state) rather than a single prefix length.
+///
+/// There may be exponentially many such sets, e.g. for `ANNN…`, so only the first
+/// [`IupacAutomat::MAX_STATES`] found are tabulated. Beyond them the search steps the
+/// Shift-And state itself until it reaches a tabulated one again.
+pub struct IupacAutomat{
+    /// 256 successors per state, `UNBUILT` for states beyond the cap
+    transitions: Vec<usize>,
+    /// Shift-And state of every tabulated state
+    sets: Vec<u64>,
+    states: HashMap<u64, usize>,
+    masks: Vec<u64>,
+    m: usize
+}
+
+const UNBUILT: usize = usize::MAX;
+
+impl IupacAutomat{
+    pub const MAX_STATES: usize = 1 << 10;
+
+    /// `None` if the pattern is empty or longer than 64 symbols.
+    pub fn new(pattern: &IupacPattern) -> Option<Self> {
+        if pattern.is_empty() || pattern.len() > 64 {
+            return None;
+        }
+        let masks = pattern.masks();
+        let mut states: HashMap<u64, usize> = HashMap::new();
+        states.insert(0, 0);
+        let mut queue = VecDeque::from(vec![0u64]);
+        let mut transitions = vec![];
+        let mut sets = vec![0];
+        while let Some(d) = queue.pop_front() {
+            for mask in &masks {
+                let next = ((d << 1) | 1) & mask;
+                let id = match states.get(&next) {
+                    Some(id) => *id,
+                    None if sets.len() < Self::MAX_STATES => {
+                        states.insert(next, sets.len());
+                        queue.push_back(next);
+                        sets.push(next);
+                        sets.len() - 1
+                    },
+                    None => UNBUILT
+                };
+                transitions.push(id);
+            }
+        }
+        Some(Self{
+            transitions,
+            sets,
+            states,
+            masks,
+            m: pattern.len()
+        })
+    }
+}
+
+impl TextMatch<u8> for IupacAutomat {
+    fn pattern_len(&self) -> usize {
+        self.m
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [u8]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let accept = 1 << (self.m - 1);
+        let (mut state, mut d) = (0, 0u64);
+        Box::new(text.iter().enumerate().filter_map(move |(idx, char)| {
+            let next = if state == UNBUILT { UNBUILT } else { self.transitions[(state << 8) + *char as usize] };
+            if next == UNBUILT {
+                d = ((d << 1) | 1) & self.masks[*char as usize];
+                state = self.states.get(&d).copied().unwrap_or(UNBUILT);
+            } else {
+                state = next;
+                d = self.sets[state];
+            }
+            if d & accept != 0 {
+                Some(idx+1-self.m)
+            } else {
+                None
+            }
+        }))
+    }
+}
+
+#[test]
+fn test_reverse_complement() {
+    assert_eq!(b"GAATTC".to_vec(), reverse_complement(b"GAATTC"));
+    assert_eq!(b"TAATAC".to_vec(), reverse_complement(b"GTATTA"));
+    assert_eq!(vec!['c', 'g', 'N', 'R'], reverse_complement(&['Y', 'N', 'c', 'g']));
+}
+
+#[test]
+fn test_strand_match() {
+    use crate::string_match::{Automat, BoyerMoore};
+    let text = b"GTATTACCTAATACGAATTC";
+    let strands = StrandMatch::new(b"GTATTA", BoyerMoore::new);
+    assert_eq!(vec![(0, Strand::Forward), (8, Strand::Reverse)], strands.match_strands(text));
+    let palindrome = StrandMatch::new(b"GAATTC", Automat::new);
+    assert_eq!(vec![(14, Strand::Forward)], palindrome.match_strands(text));
+    assert_eq!(vec![Match::new(0, 6), Match::new(8, 14).with_strand(Strand::Reverse)], strands.find_matches(text));
+    // The reverse hit at 4 overlaps the forward one at 0
+    let text = b"GTATTAATAC";
+    assert_eq!(vec![(0, Strand::Forward), (4, Strand::Reverse)], strands.match_strands_with(text, MatchSemantics::Overlapping));
+    assert_eq!(vec![(0, Strand::Forward)], strands.match_strands_with(text, MatchSemantics::NonOverlapping));
+    let iupac = |pattern: &[u8]| IupacPattern::new(pattern, NPolicy::Mismatch);
+    assert_eq!(vec![(0, Strand::Forward), (4, Strand::Reverse)], StrandMatch::new(b"GTRTTA", iupac).transpose().unwrap().match_strands(text));
+    assert!(StrandMatch::new(b"GTXTTA", iupac).transpose().is_none());
+}
+
+#[test]
+fn test_strand_patterns() {
+    use crate::string_match::{AhoCorasick, MultiMatch};
+    let text = b"GTATTACCTAATACGAATTC";
+    let strands = StrandPatterns::new(&[&b"GTATTA"[..], b"GAATTC"]);
+    assert_eq!(3, strands.patterns().len());
+    let expected = vec![Match::new(0, 6), Match::new(8, 14).with_strand(Strand::Reverse), Match::new(14, 20).with_pattern(1)];
+    assert_eq!(expected, strands.tag(AhoCorasick::new(strands.patterns()).find_matches(text)));
+    let forward = StrandPatterns::forward(&[&b"GTATTA"[..], b"GAATTC"]);
+    assert_eq!(vec![expected[0], expected[2]], forward.tag(AhoCorasick::new(forward.patterns()).find_matches(text)));
+}
+
+#[test]
+fn test_iupac() {
+    let text = b"GGACCAAATTCGAATTTCCAGGCCTGGNNACC";
+    for (pattern, expected) in [("GGNCC", vec![0]), ("RAATTY", vec![5, 11]), ("CCWGG", vec![17, 22])] {
+        let pattern = IupacPattern::new(pattern.as_bytes(), NPolicy::Mismatch).unwrap();
+        assert_eq!(expected, pattern.match_text(text));
+        assert_eq!(expected, pattern.shift_or().match_text(text));
+        assert_eq!(expected, IupacAutomat::new(&pattern).unwrap().match_text(text));
+    }
+    assert!(IupacPattern::new(b"GAXTC", NPolicy::Mismatch).is_none());
+}
+
+#[test]
+fn test_iupac_automat_limits() {
+    let empty = IupacPattern::new(b"", NPolicy::Mismatch).unwrap();
+    assert!(IupacAutomat::new(&empty).is_none());
+    assert!(empty.match_text(b"ACGT").is_empty());
+    assert!(IupacAutomat::new(&IupacPattern::new(&[b'A'; 65], NPolicy::Mismatch).unwrap()).is_none());
+    assert!(IupacAutomat::new(&IupacPattern::new(&[b'A'; 64], NPolicy::Mismatch).unwrap()).is_some());
+    // Every subset of the N positions is a reachable state, far more than are tabulated
+    let mut pattern = b"A".to_vec();
+    pattern.extend_from_slice(&[b'N'; 24]);
+    let pattern = IupacPattern::new(pattern.as_slice(), NPolicy::Mismatch).unwrap();
+    let text: Vec<u8> = (0..2000u32).map(|i| b"ACGT"[(i*i % 7 % 4) as usize]).collect();
+    assert_eq!(pattern.match_text(text.as_slice()), IupacAutomat::new(&pattern).unwrap().match_text(text.as_slice()));
+}
+
+#[test]
+fn test_n_policy() {
+    let text = b"GGNNACC";
+    let matched = |pattern: &[u8], policy| IupacPattern::new(pattern, policy).unwrap().match_text(text);
+    assert_eq!(Vec::<usize>::new(), matched(b"GNNA", NPolicy::Mismatch));
+    assert_eq!(vec![1], matched(b"GNNA", NPolicy::Strict));
+    assert_eq!(Vec::<usize>::new(), matched(b"GACA", NPolicy::Strict));
+    assert_eq!(vec![1], matched(b"GACA", NPolicy::Match));
+}
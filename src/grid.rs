@@ -0,0 +1,82 @@
+use indexmap::IndexSet;
+
+use crate::string_match::{AhoCorasick, KnuthMorrisPratt, MultiMatch, Symbol, TextMatch};
+
+/// Baker–Bird two-dimensional matching of an m×m' block in an n×n' grid, O(nn' + mm').
+///
+/// Distinct pattern rows get an id and are searched in every grid row with a single
+/// Aho-Corasick automaton. Writing the id of the row ending at each cell turns every grid
+/// column into a string, in which KMP looks for the ids of the pattern rows from top to bottom.
+pub struct BakerBird<T>{
+    rows: AhoCorasick<T>,
+    columns: KnuthMorrisPratt<Option<usize>>,
+    width: usize
+}
+
+impl<T> BakerBird<T> where T: Symbol {
+    /// Panics if the pattern is empty or its rows differ in length.
+    pub fn new<R>(pattern: &[R]) -> Self where R: AsRef<[T]> {
+        let width = pattern.first().map(|r| r.as_ref().len()).unwrap_or(0);
+        assert!(width > 0, "pattern has to be a non-empty block");
+        assert!(pattern.iter().all(|r| r.as_ref().len() == width), "pattern rows have to be of equal length");
+        let mut distinct: IndexSet<&[T]> = IndexSet::new();
+        let ids: Vec<Option<usize>> = pattern.iter().map(|r| Some(distinct.insert_full(r.as_ref()).0)).collect();
+        let distinct: Vec<&[T]> = distinct.into_iter().collect();
+        Self{
+            rows: AhoCorasick::new(distinct.as_slice()),
+            columns: KnuthMorrisPratt::new(ids.as_slice()),
+            width
+        }
+    }
+
+    /// Top left corners `(row, column)` of all occurrences, sorted by row and column.
+    pub fn match_grid<R>(&self, grid: &[R]) -> Vec<(usize, usize)> where R: AsRef<[T]> {
+        let columns = grid.iter().map(|r| r.as_ref().len()).max().unwrap_or(0);
+        // ids[c][r] is the id of the pattern row ending in grid row r at column c
+        let mut ids = vec![vec![None; grid.len()]; columns];
+        for (r, row) in grid.iter().enumerate() {
+            for (id, pos) in self.rows.match_patterns(row.as_ref()) {
+                ids[pos+self.width-1][r] = Some(id);
+            }
+        }
+        let mut matches = vec![];
+        for (c, column) in ids.iter().enumerate().skip(self.width-1) {
+            for r in self.columns.find_iter(column) {
+                matches.push((r, c+1-self.width));
+            }
+        }
+        matches.sort_unstable();
+        matches
+    }
+}
+
+#[test]
+fn test_baker_bird() {
+    let grid = [
+        "abaabab",
+        "bababaa",
+        "abaabab",
+        "babbaba",
+        "abaabab",
+        "bab"
+    ];
+    let grid: Vec<&[u8]> = grid.iter().map(|r| r.as_bytes()).collect();
+    let pattern: [&[u8]; 3] = [b"aba", b"bab", b"aba"];
+    assert_eq!(vec![(0, 0), (2, 0), (2, 3)], BakerBird::new(&pattern).match_grid(&grid));
+}
+
+#[test]
+fn test_baker_bird_naive() {
+    let grid: Vec<Vec<u8>> = crate::string_match::random_text(7, 40*50, b"ab").chunks(50).map(|row| row.to_vec()).collect();
+    let pattern: Vec<Vec<u8>> = grid[10..12].iter().map(|r| r[20..23].to_vec()).collect();
+    let mut naive = vec![];
+    for r in 0..=grid.len()-pattern.len() {
+        for c in 0..=grid[0].len()-3 {
+            if pattern.iter().enumerate().all(|(i, p)| &grid[r+i][c..c+3] == p.as_slice()) {
+                naive.push((r, c));
+            }
+        }
+    }
+    assert!(naive.contains(&(10, 20)));
+    assert_eq!(naive, BakerBird::new(&pattern).match_grid(&grid));
+}
@@ -1,3 +1,11 @@
 pub mod string_match;
 pub mod metrics;
-pub mod suffix;
\ No newline at end of file
+pub mod suffix;
+pub mod dna;
+pub mod approximate;
+pub mod z_algorithm;
+pub mod wildcard;
+pub mod grid;
+pub mod parallel;
+pub mod algorithm;
+pub mod persist;
\ No newline at end of file
@@ -1,12 +1,14 @@
+use std::{fmt, iter};
+
 use colored::Colorize;
 use dialoguer::Select;
 use seq_io::fasta::Reader;
 
-use ambi::string_match::{self, TextMatch};
+use ambi::{algorithm::{Algorithm, Approximate, MultiAlgorithm}, dna::{StrandMatch, StrandPatterns}, parallel, string_match::{Match, MatchSemantics, TextMatch}};
 
 #[derive(Clone)]
 enum SampleSource{
-    Direct(Vec<char>),
+    Direct(Vec<u8>),
     Record(String)
 }
 
@@ -16,6 +18,84 @@ struct SampleSet{
     patterns: Vec<String> 
 }
 
+/// What the CLI searches with, every registry of the library and `str::find` to compare against.
+#[derive(Clone, Copy)]
+enum Choice{
+    Exact(Algorithm),
+    Multi(MultiAlgorithm),
+    Approximate(Approximate),
+    Native
+}
+
+impl fmt::Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Choice::Exact(algorithm) => write!(f, "{}", algorithm),
+            Choice::Multi(algorithm) => write!(f, "{}", algorithm),
+            Choice::Approximate(algorithm) => write!(f, "{}", algorithm),
+            Choice::Native => write!(f, "Native")
+        }
+    }
+}
+
+/// All matches in a text, sorted by position.
+type Search = Box<dyn Fn(&[u8]) -> Vec<Match>>;
+
+/// A search prepared by the CLI, a [`TextMatch`] so library helpers like [`StrandMatch`] drive it.
+struct Prepared{
+    pattern_len: usize,
+    search: Search
+}
+
+impl TextMatch<u8> for Prepared {
+    fn pattern_len(&self) -> usize {
+        self.pattern_len
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [u8]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        Box::new((self.search)(text).into_iter().map(|m| m.start))
+    }
+
+    fn find_matches(&self, text: &[u8]) -> Vec<Match> {
+        (self.search)(text)
+    }
+}
+
+/// Preprocess `pattern` for a single pattern search, None for multi pattern searches,
+/// which take all patterns at once, and for patterns the matcher can't take.
+fn prepare(choice: Choice, pattern: &[u8], sigma: &[u8], k: usize, threads: usize) -> Option<Prepared> {
+    let pattern_len = pattern.len();
+    let search: Search = match choice {
+        Choice::Exact(algorithm) => {
+            let matcher = algorithm.build(pattern, sigma);
+            Box::new(move |text| parallel::par_match(matcher.as_ref(), text, threads).into_iter().map(|s| Match::new(s, s+pattern_len)).collect())
+        },
+        Choice::Approximate(algorithm) => {
+            let matcher = algorithm.build(pattern, k)?;
+            Box::new(move |text| matcher.find_matches(text))
+        },
+        Choice::Multi(_) => return None,
+        Choice::Native => {
+            let pattern = String::from_utf8_lossy(pattern).into_owned();
+            Box::new(move |text| {
+                let text = String::from_utf8_lossy(text);
+                // match_indices skips overlapping occurrences, restart one symbol after each hit
+                let mut what = vec![];
+                let mut start = 0;
+                while let Some(idx) = text[start..].find(pattern.as_str()) {
+                    what.push(Match::new(start+idx, start+idx+pattern_len));
+                    start += idx + text[start+idx..].chars().next().map_or(1, char::len_utf8);
+                }
+                what
+            })
+        }
+    };
+    Some(Prepared{
+        pattern_len,
+        search
+    })
+}
+
 pub enum MatchingState{
     Performance,
     Debug
@@ -66,7 +146,7 @@ fn main() {
         let set = if index < 3 {
             sample_sets[index].clone()
         } else {
-            let target: String = dialoguer::Input::new().with_prompt("Enter a text to search in: [There is currently a bug with umlauts and other special characters]").interact_text().expect("You had to input a sequence...");
+            let target: String = dialoguer::Input::new().with_prompt("Enter a text to search in").interact_text().expect("You had to input a sequence...");
             let mut patterns = vec![];
             while let Ok(p) = dialoguer::Input::new().with_prompt("Enter a pattern or nothing to end the input sequence").allow_empty(true).interact_text() {
                 let result: String = p;
@@ -78,108 +158,119 @@ fn main() {
             }
             SampleSet{
                 target: SampleSource::Direct(
-                    target.chars().collect()
+                    target.into_bytes()
                 ),
                 patterns
             }
         };
         println!("Choosen scenario: {}", scenario_options[index].to_string().red());
+        let choices = Algorithm::ALL.iter().map(|a| Choice::Exact(*a))
+            .chain(MultiAlgorithm::ALL.iter().map(|a| Choice::Multi(*a)))
+            .chain(Approximate::ALL.iter().map(|a| Choice::Approximate(*a)))
+            .chain(iter::once(Choice::Native))
+            .collect::<Vec<Choice>>();
+        let choice = match Select::new().items(&choices).with_prompt("Choose a matching algorithmn").interact() {
+            Ok(i) => choices[i],
+            Err(_) => continue
+        };
+        let both_strands = matches!(index, 1 | 2) && dialoguer::Confirm::new().with_prompt("Search the reverse strand as well?").interact().unwrap_or(false);
+        let threads = match choice {
+            Choice::Exact(_) | Choice::Multi(_) if dialoguer::Confirm::new().with_prompt("Split the search across all cores?").interact().unwrap_or(false) => 0,
+            _ => 1
+        };
+        let k = match choice {
+            Choice::Approximate(algorithm) if algorithm.uses_k() => dialoguer::Input::new().with_prompt("Allowed mismatches or edits").default(1).interact_text().unwrap_or(1),
+            _ => 0
+        };
+        let semantics = match Select::new().items(&["Overlapping", "Non-overlapping", "Leftmost longest"]).default(0).with_prompt("Choose which occurrences to report").interact() {
+            Ok(1) => MatchSemantics::NonOverlapping,
+            Ok(2) => MatchSemantics::LeftmostLongest,
+            _ => MatchSemantics::Overlapping
+        };
 
-        let text: Vec<char> = match set.target {
+        let text: Vec<u8> = match set.target {
             SampleSource::Direct(t) => t,
             SampleSource::Record(path) => {
                 let mut sequence = vec![];
                 if let Some(Ok(rec)) = Reader::from_path(path).ok().and_then(|r| r.into_records().next()) {
-                    sequence = rec.seq.iter().map(|x| *x as char).collect();
+                    sequence = rec.seq;
                 }
                 sequence
             }
         };
 
         // Match set
-        let prepare_matcher: Box<dyn Fn(Vec<char>, &[char]) -> Box<dyn Fn(&Vec<char>) -> Vec<usize>>> = match Select::new().items(&["Naive", "Rabin Karp", "Automaton", "Knuth Morris Pratt", "Boyer Moore", "Native"]).with_prompt("Choose a matching algorithmn").interact() {
-            Ok(0) => {
-                // Naive
-                println!("{}","NAIVE".to_string().red());
-                Box::new(|pattern, sigma| {
-                    Box::new(move |text| {
-                        string_match::naive_match(text, &pattern)
-                    })
-                })
-            },
-            Ok(1) => {
-                // Rabin Karp
-                Box::new(|pattern, sigma| {
-                    let rk = string_match::RabinKarp::new(pattern.as_slice(), sigma);
-                    Box::new(move |text| {
-                        // string_match::rabin_karp(text, &pattern, 2, 101);
-                        rk.match_text(text)
-                    })
-                })
-            },
-            Ok(2) => {
-                // Automaton
-                Box::new(|pattern, _sigma| {
-                    let automaton = string_match::Automat::new(pattern.as_slice());
-                    Box::new(move |text| automaton.match_text(text.as_slice()))
-                })
-            },
-            Ok(3) => {
-                // Knuth Morris Pratt
-                Box::new(|pattern, _sigma| {
-                    let knuth_morris_pratt = string_match::KnuthMorrisPratt::new(pattern.as_slice());
-                    Box::new(move |text| knuth_morris_pratt.match_text(text.as_slice()))
-                })
-            },
-            Ok(4) => {
-                // Boyer Moore
-                Box::new(|pattern, _sigma| {
-                    let bm = string_match::BoyerMoore::new(&pattern);
-                    Box::new(move |text| bm.match_text(text))
-                })
-            },
-            _ => {
-                Box::new(|pattern: Vec<char>, _sigma: &[char]| {
-                    Box::new(move |text: &Vec<char>| {
-                        let text = text.iter().collect::<String>();
-                        let pattern = pattern.iter().collect::<String>();
-                        let what = text.as_str().match_indices(pattern.as_str()).map(|(idx, _)| idx).collect::<Vec<usize>>();
-                        what
-                    })
-                })
+        if let Choice::Multi(algorithm) = choice {
+            // All patterns of the set, and their reverse complements, in a single pass
+            let patterns: Vec<&[u8]> = set.patterns.iter().map(|p| p.as_bytes()).collect();
+            let now = std::time::Instant::now();
+            let strands = if both_strands {
+                StrandPatterns::new(patterns.as_slice())
+            } else {
+                StrandPatterns::forward(patterns.as_slice())
+            };
+            let matcher = algorithm.build(strands.patterns());
+            let lengths = matcher.pattern_lens();
+            let elapsed = now.elapsed().as_micros();
+            println!("Prepared for search of {} patterns in {}μs", patterns.len(), elapsed.to_string().yellow());
+            let now = std::time::Instant::now();
+            let found = parallel::par_match_patterns(matcher.as_ref(), &text, threads).into_iter()
+                .map(|(id, s)| Match::new(s, s+lengths[id]).with_pattern(id))
+                .collect();
+            let result = semantics.select_matches(strands.tag(found));
+            let elapsed = now.elapsed().as_micros();
+            for (id, pattern) in set.patterns.iter().enumerate() {
+                let matches = result.iter().filter(|m| m.pattern == id).copied().collect::<Vec<Match>>();
+                println!("Pattern P {}", pattern.to_string().yellow());
+                println!("Found pattern P at [{}]", format_matches(matches.as_slice(), both_strands));
             }
-        };
+            println!("Searched for all patterns in {}μs", elapsed.to_string().yellow());
+            continue;
+        }
 
         for pattern in &set.patterns {
-            let chars: Vec<char> = pattern.chars().collect();
+            let chars: Vec<u8> = pattern.as_bytes().to_vec();
 
             // Alphabet
-            let mut sigma = text.iter().chain(chars.iter()).cloned().collect::<Vec<char>>();
+            let mut sigma = text.iter().chain(chars.iter()).cloned().collect::<Vec<u8>>();
             sigma.sort_unstable();
             sigma.dedup();
 
-            // Prepare pattern
+            // Prepare pattern, and its reverse complement unless it is palindromic
             println!("Pattern P {}", pattern.to_string().yellow());
             let now = std::time::Instant::now();
-            let match_text = prepare_matcher(chars, sigma.as_slice());
+            let build = |pattern: &[u8]| prepare(choice, pattern, sigma.as_slice(), k, threads);
+            let strands = if both_strands {
+                StrandMatch::new(chars.as_slice(), build)
+            } else {
+                StrandMatch::forward(build(chars.as_slice()))
+            };
+            let strands = match strands.transpose() {
+                Some(strands) => strands,
+                None => {
+                    println!("{} can't search for pattern P", choice.to_string().red());
+                    continue;
+                }
+            };
             let elapsed = now.elapsed().as_micros();
             println!("Prepared for search of pattern P in {}μs", elapsed.to_string().yellow());
-            // Match against text
+            // Match against text, both strands together select the reported occurrences
             let now = std::time::Instant::now();
-            let result = match_text(&text);
+            let result = strands.find_matches_with(&text, semantics);
             let elapsed = now.elapsed().as_micros();
-            println!("Found pattern P at indices [{}] in {}μs", format_char_vec(result.as_slice()), elapsed.to_string().yellow());
+            println!("Found pattern P at [{}] in {}μs", format_matches(result.as_slice(), both_strands), elapsed.to_string().yellow());
         }
     }
 }
 
-fn format_char_vec(input: &[usize]) -> String {
-    let mut string: String = "".into();
-    for payload in input.iter().skip(1) {
-        string = format!("{}, {}", string, (*payload).to_string().green());
-    };
-    if let Some(p) = input.iter().next() {
-        string = format!("{}{}", (*p).to_string().green(), string);
-    }
-    string
+/// Spans of the matches, tagged with their strand if `strands` is set.
+fn format_matches(input: &[Match], strands: bool) -> String {
+    input.iter().map(|m| {
+        let span = format!("{}..{}", m.start, m.end).green();
+        let span = if strands { format!("{}{}", m.strand, span) } else { span.to_string() };
+        match m.score {
+            Some(score) => format!("{} ({} edits)", span, score),
+            None => span
+        }
+    }).collect::<Vec<String>>().join(", ")
 }
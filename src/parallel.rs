@@ -0,0 +1,127 @@
+use std::thread;
+
+use crate::string_match::{MatchSemantics, MultiMatch, TextMatch};
+
+/// Search `text` with `threads` workers, 0 meaning one per available core.
+///
+/// Every worker gets a contiguous range of start positions and reads m-1 symbols beyond
+/// it, so a match is found by exactly the worker owning its start. The merged positions
+/// are sorted and free of duplicates, just like a sequential `match_text`.
+pub fn par_match<T, M>(matcher: &M, text: &[T], threads: usize) -> Vec<usize> where T: Sync, M: TextMatch<T> + Sync + ?Sized {
+    let pattern_len = matcher.pattern_len();
+    let threads = match threads {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n
+    };
+    let starts = (text.len()+1).saturating_sub(pattern_len.max(1));
+    // Chunks far shorter than the pattern would mostly scan overlap
+    let chunk = starts.div_ceil(threads).max(pattern_len).max(1);
+    if starts <= chunk {
+        return matcher.match_text(text);
+    }
+    let mut matches: Vec<usize> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..starts).step_by(chunk).map(|begin| {
+            let end = (begin+chunk+pattern_len.saturating_sub(1)).min(text.len());
+            scope.spawn(move || {
+                matcher.find_iter(&text[begin..end]).map(|pos| pos+begin).collect::<Vec<usize>>()
+            })
+        }).collect();
+        workers.into_iter().flat_map(|w| w.join().expect("matcher panicked")).collect()
+    });
+    matches.sort_unstable();
+    matches.dedup();
+    matches
+}
+
+/// [`par_match`] reporting the matches selected by `semantics`.
+pub fn par_match_with<T, M>(matcher: &M, text: &[T], semantics: MatchSemantics, threads: usize) -> Vec<usize> where T: Sync, M: TextMatch<T> + Sync + ?Sized {
+    semantics.select(par_match(matcher, text, threads), matcher.pattern_len()).collect()
+}
+
+/// Search `text` for a set of patterns with `threads` workers, 0 meaning one per available core.
+///
+/// Like [`par_match`], every worker owns a range of start positions and reads as far beyond it
+/// as the longest pattern needs. The `(pattern_id, position)` pairs are sorted by position and
+/// pattern id, just like a sequential `match_patterns`.
+pub fn par_match_patterns<T, M>(matcher: &M, text: &[T], threads: usize) -> Vec<(usize, usize)> where T: Sync, M: MultiMatch<T> + Sync + ?Sized {
+    let longest = matcher.pattern_lens().into_iter().max().unwrap_or(0);
+    let threads = match threads {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n
+    };
+    let chunk = text.len().div_ceil(threads).max(longest).max(1);
+    if text.len() <= chunk {
+        return matcher.match_patterns(text);
+    }
+    let mut matches: Vec<(usize, usize)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..text.len()).step_by(chunk).map(|begin| {
+            let end = (begin+chunk+longest.saturating_sub(1)).min(text.len());
+            scope.spawn(move || {
+                matcher.match_patterns(&text[begin..end]).into_iter()
+                    .filter(|(_, pos)| *pos < chunk)
+                    .map(|(id, pos)| (id, pos+begin))
+                    .collect::<Vec<(usize, usize)>>()
+            })
+        }).collect();
+        workers.into_iter().flat_map(|w| w.join().expect("matcher panicked")).collect()
+    });
+    matches.sort_unstable_by_key(|(id, pos)| (*pos, *id));
+    matches
+}
+
+#[test]
+fn test_par_match() {
+    use crate::string_match::{BoyerMoore, KnuthMorrisPratt, naive_match};
+    let text = b"AAAAAGAATTCAAAAGAATTCGAATTCAAAAAAAAAAGAATTC".repeat(50);
+    let pattern = b"GAATTC";
+    let expected = naive_match(text.as_slice(), pattern);
+    for threads in [0, 1, 2, 3, 7, 64, 1000] {
+        assert_eq!(expected, par_match(&BoyerMoore::new(pattern), text.as_slice(), threads));
+    }
+    // Self overlapping matches around chunk borders
+    let text = vec![b'A'; 1001];
+    let matcher = KnuthMorrisPratt::new(b"AAAA");
+    assert_eq!(naive_match(text.as_slice(), b"AAAA"), par_match(&matcher, text.as_slice(), 9));
+    assert_eq!(vec![0, 4, 8, 12], par_match_with(&matcher, &text[..16], MatchSemantics::NonOverlapping, 2));
+}
+
+#[test]
+fn test_par_match_patterns() {
+    use crate::string_match::{AhoCorasick, MultiRabinKarp};
+    let text = b"AAAAAGAATTCAAAAGAATTCGAATTCAAAAAAAAAAGAATTC".repeat(50);
+    let patterns: [&[u8]; 4] = [b"GAATTC", b"AAAA", b"C", b"TCGAATTCAAAAAAAA"];
+    let ac = AhoCorasick::new(&patterns);
+    let expected = ac.match_patterns(text.as_slice());
+    for threads in [0, 1, 2, 3, 7, 64, 1000] {
+        assert_eq!(expected, par_match_patterns(&ac, text.as_slice(), threads));
+        assert_eq!(expected, par_match_patterns(&MultiRabinKarp::new(&patterns), text.as_slice(), threads));
+    }
+}
+
+#[test]
+fn test_matchers_send_sync() {
+    use crate::{approximate, dna, grid, string_match, wildcard, z_algorithm};
+    fn assert_send_sync<M: Send + Sync>() {}
+    assert_send_sync::<string_match::Naive<u8>>();
+    assert_send_sync::<string_match::RabinKarp<u8>>();
+    assert_send_sync::<string_match::MultiRabinKarp<u8>>();
+    assert_send_sync::<string_match::Automat<u8>>();
+    assert_send_sync::<string_match::DenseAutomat<u8>>();
+    assert_send_sync::<string_match::KnuthMorrisPratt<u8>>();
+    assert_send_sync::<string_match::RealTimeKmp<u8>>();
+    assert_send_sync::<string_match::BoyerMoore<u8>>();
+    assert_send_sync::<string_match::Horspool<u8>>();
+    assert_send_sync::<string_match::Sunday<u8>>();
+    assert_send_sync::<string_match::ShiftOr<u8>>();
+    assert_send_sync::<string_match::ByteFinder<u8>>();
+    assert_send_sync::<string_match::AhoCorasick<u8>>();
+    assert_send_sync::<z_algorithm::ZMatcher<u8>>();
+    assert_send_sync::<dna::IupacPattern>();
+    assert_send_sync::<dna::IupacAutomat>();
+    assert_send_sync::<dna::StrandMatch<string_match::BoyerMoore<u8>>>();
+    assert_send_sync::<approximate::KMismatch<u8>>();
+    assert_send_sync::<approximate::KDifference<u8>>();
+    assert_send_sync::<approximate::Myers<u8>>();
+    assert_send_sync::<wildcard::Wildcard<u8>>();
+    assert_send_sync::<grid::BakerBird<u8>>();
+}
@@ -0,0 +1,474 @@
+use std::{collections::HashMap, convert::TryFrom, io::{self, Read, Write}};
+
+use crate::string_match::{AhoCorasick, Automat, BoyerMoore, KnuthMorrisPratt, RabinKarp, RollingHash};
+
+const MAGIC: &[u8; 4] = b"AMBI";
+/// Bumped whenever the layout of a payload changes, older files are rejected.
+pub const VERSION: u16 = 1;
+
+/// Matcher stored in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind{
+    Automaton = 1,
+    KnuthMorrisPratt = 2,
+    BoyerMoore = 3,
+    RabinKarp = 4,
+    AhoCorasick = 5
+}
+
+impl Kind{
+    fn from_u8(b: u8) -> Option<Self> {
+        [Kind::Automaton, Kind::KnuthMorrisPratt, Kind::BoyerMoore, Kind::RabinKarp, Kind::AhoCorasick].iter().copied().find(|k| *k as u8 == b)
+    }
+}
+
+/// Everything in front of the payload, readable without knowing the matcher type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header{
+    pub version: u16,
+    pub kind: Kind,
+    /// Distinct symbols of the pattern(s) in increasing order
+    pub alphabet: Vec<u8>
+}
+
+/// Byte matchers whose preprocessed tables can be written to disk and loaded again.
+///
+/// A file is the magic `AMBI`, the format version (u16), the kind (u8), the alphabet and the
+/// payload (each a u64 length and bytes) and an FNV-1a checksum (u64) over all of it.
+/// Integers are little endian, `usize` is stored as u64.
+pub trait Persist: Sized {
+    const KIND: Kind;
+
+    fn write_payload(&self, out: &mut Vec<u8>);
+
+    /// Rebuild the matcher, rejecting tables it could panic or loop on.
+    fn read_payload(input: &mut &[u8]) -> io::Result<Self>;
+
+    /// Distinct symbols in increasing order.
+    fn alphabet(&self) -> Vec<u8>;
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |h, b| (h ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+fn put_u64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_usizes(out: &mut Vec<u8>, values: &[usize]) {
+    put_u64(out, values.len() as u64);
+    for v in values {
+        put_u64(out, *v as u64);
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_u64(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> io::Result<&'a [u8]> {
+    if input.len() < n {
+        return Err(invalid("unexpected end of data"));
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+fn take_u64(input: &mut &[u8]) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(take(input, 8)?);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn take_usize(input: &mut &[u8]) -> io::Result<usize> {
+    usize::try_from(take_u64(input)?).map_err(|_| invalid("value exceeds usize"))
+}
+
+/// A length prefix, bounded by the remaining input so a corrupt one can't allocate gigabytes.
+fn take_len(input: &mut &[u8], item_size: usize) -> io::Result<usize> {
+    let len = take_usize(input)?;
+    if len > input.len() / item_size.max(1) {
+        return Err(invalid("length exceeds data"));
+    }
+    Ok(len)
+}
+
+fn take_usizes(input: &mut &[u8]) -> io::Result<Vec<usize>> {
+    let len = take_len(input, 8)?;
+    (0..len).map(|_| take_usize(input)).collect()
+}
+
+fn take_bytes(input: &mut &[u8]) -> io::Result<Vec<u8>> {
+    let len = take_len(input, 1)?;
+    Ok(take(input, len)?.to_vec())
+}
+
+fn sorted_alphabet<I>(symbols: I) -> Vec<u8> where I: IntoIterator<Item = u8> {
+    let mut alphabet: Vec<u8> = symbols.into_iter().collect();
+    alphabet.sort_unstable();
+    alphabet.dedup();
+    alphabet
+}
+
+/// Write `matcher` with header and checksum.
+pub fn save<M, W>(matcher: &M, mut writer: W) -> io::Result<()> where M: Persist, W: Write {
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.push(M::KIND as u8);
+    put_bytes(&mut out, &matcher.alphabet());
+    let mut payload = vec![];
+    matcher.write_payload(&mut payload);
+    put_bytes(&mut out, &payload);
+    let checksum = fnv1a(&out);
+    put_u64(&mut out, checksum);
+    writer.write_all(&out)
+}
+
+/// Verify magic and checksum and split a file into header and payload.
+fn split(data: &[u8]) -> io::Result<(Header, &[u8])> {
+    if data.len() < MAGIC.len() + 8 || &data[..MAGIC.len()] != MAGIC {
+        return Err(invalid("not a matcher file"));
+    }
+    let (body, mut checksum) = data.split_at(data.len()-8);
+    if take_u64(&mut checksum)? != fnv1a(body) {
+        return Err(invalid("checksum mismatch"));
+    }
+    let mut input = &body[MAGIC.len()..];
+    let version = take(&mut input, 2)?;
+    let version = u16::from_le_bytes([version[0], version[1]]);
+    if version != VERSION {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, format!("format version {} is not supported", version)));
+    }
+    let kind = Kind::from_u8(take(&mut input, 1)?[0]).ok_or_else(|| invalid("unknown matcher kind"))?;
+    let alphabet = take_bytes(&mut input)?;
+    let len = take_len(&mut input, 1)?;
+    let payload = take(&mut input, len)?;
+    if !input.is_empty() {
+        return Err(invalid("trailing data"));
+    }
+    let header = Header{
+        version,
+        kind,
+        alphabet
+    };
+    Ok((header, payload))
+}
+
+/// Read only the header of a file, e.g. to check the alphabet before loading.
+pub fn read_header<R>(mut reader: R) -> io::Result<Header> where R: Read {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    split(&data).map(|(header, _)| header)
+}
+
+/// Read a matcher written by [`save`].
+pub fn load<M, R>(mut reader: R) -> io::Result<M> where M: Persist, R: Read {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    let (header, mut payload) = split(&data)?;
+    if header.kind != M::KIND {
+        return Err(invalid("file holds another kind of matcher"));
+    }
+    let matcher = M::read_payload(&mut payload)?;
+    if !payload.is_empty() {
+        return Err(invalid("trailing payload data"));
+    }
+    if matcher.alphabet() != header.alphabet {
+        return Err(invalid("alphabet does not match the payload"));
+    }
+    Ok(matcher)
+}
+
+impl Persist for Automat<u8> {
+    const KIND: Kind = Kind::Automaton;
+
+    fn write_payload(&self, out: &mut Vec<u8>) {
+        put_u64(out, self.accept as u64);
+        let mut transitions: Vec<(usize, u8, usize)> = self.transition_functions.iter().map(|((q, c), next)| (*q, *c, *next)).collect();
+        transitions.sort_unstable();
+        put_u64(out, transitions.len() as u64);
+        for (q, c, next) in transitions {
+            put_u64(out, q as u64);
+            out.push(c);
+            put_u64(out, next as u64);
+        }
+    }
+
+    fn read_payload(input: &mut &[u8]) -> io::Result<Self> {
+        let accept = take_usize(input)?;
+        let len = take_len(input, 17)?;
+        let mut transition_functions = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let q = take_usize(input)?;
+            let c = take(input, 1)?[0];
+            let next = take_usize(input)?;
+            if q > accept || next > accept {
+                return Err(invalid("transition to a missing state"));
+            }
+            transition_functions.insert((q, c), next);
+        }
+        Ok(Self{
+            accept,
+            transition_functions
+        })
+    }
+
+    fn alphabet(&self) -> Vec<u8> {
+        sorted_alphabet(self.transition_functions.keys().map(|(_, c)| *c))
+    }
+}
+
+impl Persist for KnuthMorrisPratt<u8> {
+    const KIND: Kind = Kind::KnuthMorrisPratt;
+
+    fn write_payload(&self, out: &mut Vec<u8>) {
+        put_bytes(out, &self.pattern);
+        put_usizes(out, &self.pi);
+    }
+
+    fn read_payload(input: &mut &[u8]) -> io::Result<Self> {
+        let pattern = take_bytes(input)?;
+        let pi = take_usizes(input)?;
+        // A border is shorter than the prefix it belongs to
+        if pi.len() != pattern.len()+1 || pi.iter().enumerate().any(|(q, k)| *k > q.saturating_sub(1)) {
+            return Err(invalid("prefix function does not fit the pattern"));
+        }
+        Ok(Self{
+            pattern,
+            pi
+        })
+    }
+
+    fn alphabet(&self) -> Vec<u8> {
+        sorted_alphabet(self.pattern.iter().copied())
+    }
+}
+
+impl Persist for BoyerMoore<u8> {
+    const KIND: Kind = Kind::BoyerMoore;
+
+    fn write_payload(&self, out: &mut Vec<u8>) {
+        put_bytes(out, &self.pattern);
+        put_usizes(out, &self.good_suffix);
+        let mut last: Vec<(u8, usize)> = self.last_occurancies.iter().map(|(c, idx)| (*c, *idx)).collect();
+        last.sort_unstable();
+        put_u64(out, last.len() as u64);
+        for (c, idx) in last {
+            out.push(c);
+            put_u64(out, idx as u64);
+        }
+    }
+
+    fn read_payload(input: &mut &[u8]) -> io::Result<Self> {
+        let pattern = take_bytes(input)?;
+        let m = pattern.len();
+        let good_suffix = take_usizes(input)?;
+        // Every shift has to advance, otherwise the search never ends
+        if good_suffix.len() != m || good_suffix.iter().any(|s| *s == 0 || *s > m) {
+            return Err(invalid("good suffix table does not fit the pattern"));
+        }
+        let len = take_len(input, 9)?;
+        let mut last_occurancies = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let c = take(input, 1)?[0];
+            let idx = take_usize(input)?;
+            if pattern.get(idx) != Some(&c) {
+                return Err(invalid("last occurrence outside of the pattern"));
+            }
+            last_occurancies.insert(c, idx);
+        }
+        Ok(Self{
+            last_occurancies,
+            good_suffix,
+            pattern
+        })
+    }
+
+    fn alphabet(&self) -> Vec<u8> {
+        sorted_alphabet(self.pattern.iter().copied())
+    }
+}
+
+impl Persist for RabinKarp<u8> {
+    const KIND: Kind = Kind::RabinKarp;
+
+    fn write_payload(&self, out: &mut Vec<u8>) {
+        put_bytes(out, &self.pattern);
+        let mut sigma: Vec<(u8, u64)> = self.sigma_map.iter().map(|(c, v)| (*c, *v)).collect();
+        sigma.sort_unstable();
+        put_u64(out, sigma.len() as u64);
+        for (c, v) in sigma {
+            out.push(c);
+            put_u64(out, v);
+        }
+        put_u64(out, self.hashes.len() as u64);
+        for hash in &self.hashes {
+            put_u64(out, hash.base);
+            put_u64(out, hash.modulus);
+        }
+    }
+
+    fn read_payload(input: &mut &[u8]) -> io::Result<Self> {
+        let pattern = take_bytes(input)?;
+        let len = take_len(input, 9)?;
+        let mut sigma_map = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let c = take(input, 1)?[0];
+            sigma_map.insert(c, take_u64(input)?);
+        }
+        let len = take_len(input, 16)?;
+        let mut hashes = Vec::with_capacity(len);
+        for _ in 0..len {
+            let (base, modulus) = (take_u64(input)?, take_u64(input)?);
+            if !(2..=1 << 63).contains(&modulus) {
+                return Err(invalid("hash modulus out of range"));
+            }
+            hashes.push(RollingHash::new(base, modulus));
+        }
+        Ok(Self{
+            pattern,
+            sigma_map,
+            hashes
+        })
+    }
+
+    /// The pattern symbols, the text alphabet `sigma` is part of the payload.
+    fn alphabet(&self) -> Vec<u8> {
+        sorted_alphabet(self.pattern.iter().copied())
+    }
+}
+
+impl Persist for AhoCorasick<u8> {
+    const KIND: Kind = Kind::AhoCorasick;
+
+    fn write_payload(&self, out: &mut Vec<u8>) {
+        put_usizes(out, &self.lengths);
+        put_usizes(out, &self.fail);
+        let mut goto: Vec<(usize, u8, usize)> = self.goto.iter().map(|((q, c), next)| (*q, *c, *next)).collect();
+        goto.sort_unstable();
+        put_u64(out, goto.len() as u64);
+        for (q, c, next) in goto {
+            put_u64(out, q as u64);
+            out.push(c);
+            put_u64(out, next as u64);
+        }
+        for ids in &self.output {
+            put_usizes(out, ids);
+        }
+    }
+
+    fn read_payload(input: &mut &[u8]) -> io::Result<Self> {
+        let lengths = take_usizes(input)?;
+        let fail = take_usizes(input)?;
+        let states = fail.len();
+        if states == 0 {
+            return Err(invalid("trie without a root"));
+        }
+        let len = take_len(input, 17)?;
+        let mut goto = HashMap::with_capacity(len);
+        // Depth of every state in the trie, each one but the root has exactly one parent
+        let mut depth: Vec<Option<usize>> = vec![None; states];
+        let mut children: Vec<Vec<usize>> = vec![vec![]; states];
+        for _ in 0..len {
+            let q = take_usize(input)?;
+            let c = take(input, 1)?[0];
+            let next = take_usize(input)?;
+            if q >= states || next >= states || next == 0 {
+                return Err(invalid("transition to a missing state"));
+            }
+            children[q].push(next);
+            goto.insert((q, c), next);
+        }
+        depth[0] = Some(0);
+        let mut stack = vec![0];
+        while let Some(q) = stack.pop() {
+            for next in &children[q] {
+                if depth[*next].is_some() {
+                    return Err(invalid("transitions do not form a trie"));
+                }
+                depth[*next] = depth[q].map(|d| d+1);
+                stack.push(*next);
+            }
+        }
+        let depth = depth.into_iter().collect::<Option<Vec<usize>>>().ok_or_else(|| invalid("transitions do not form a trie"))?;
+        // Failure links lead to a shallower state, so following them ends in the root
+        if fail[0] != 0 || fail.iter().enumerate().skip(1).any(|(q, f)| *f >= states || depth[*f] >= depth[q]) {
+            return Err(invalid("failure links do not fit the trie"));
+        }
+        let mut output = Vec::with_capacity(states);
+        for d in &depth {
+            let ids = take_usizes(input)?;
+            if ids.iter().any(|id| lengths.get(*id).is_none_or(|l| l > d)) {
+                return Err(invalid("output of an unknown pattern"));
+            }
+            output.push(ids);
+        }
+        Ok(Self{
+            goto,
+            fail,
+            output,
+            lengths
+        })
+    }
+
+    fn alphabet(&self) -> Vec<u8> {
+        sorted_alphabet(self.goto.keys().map(|(_, c)| *c))
+    }
+}
+
+#[test]
+fn test_persist_round_trip() {
+    use crate::string_match::{MultiMatch, TextMatch};
+    let text = b"TTATTATGAATTCTTATTATGGATCC";
+    let pattern = b"TTATTAT";
+    fn round_trip<M: Persist>(matcher: &M) -> M {
+        let mut file = vec![];
+        save(matcher, &mut file).unwrap();
+        load(file.as_slice()).unwrap()
+    }
+    let expected = vec![0, 13];
+    assert_eq!(expected, round_trip(&Automat::new(pattern)).match_text(text));
+    assert_eq!(expected, round_trip(&KnuthMorrisPratt::new(pattern)).match_text(text));
+    assert_eq!(expected, round_trip(&BoyerMoore::new(pattern)).match_text(text));
+    assert_eq!(expected, round_trip(&RabinKarp::new(pattern, b"ACGT")).match_text(text));
+    let patterns: [&[u8]; 3] = [b"GAATTC", b"GGATCC", b"TTAT"];
+    let ac = AhoCorasick::new(&patterns);
+    assert_eq!(ac.match_patterns(text), round_trip(&ac).match_patterns(text));
+
+    let mut file = vec![];
+    save(&ac, &mut file).unwrap();
+    assert_eq!(Header{ version: VERSION, kind: Kind::AhoCorasick, alphabet: b"ACGT".to_vec() }, read_header(file.as_slice()).unwrap());
+}
+
+#[test]
+fn test_persist_corrupt() {
+    let mut file = vec![];
+    save(&BoyerMoore::new(b"GAATTC"), &mut file).unwrap();
+    assert!(load::<KnuthMorrisPratt<u8>, _>(file.as_slice()).is_err());
+    assert!(load::<BoyerMoore<u8>, _>(&file[..file.len()-1]).is_err());
+    for i in 0..file.len() {
+        let mut corrupt = file.clone();
+        corrupt[i] ^= 0x10;
+        assert!(load::<BoyerMoore<u8>, _>(corrupt.as_slice()).is_err());
+    }
+    // A consistent checksum doesn't make a zero shift acceptable
+    let mut payload = vec![];
+    put_bytes(&mut payload, b"AA");
+    put_usizes(&mut payload, &[0, 1]);
+    put_u64(&mut payload, 0);
+    let mut file = MAGIC.to_vec();
+    file.extend_from_slice(&VERSION.to_le_bytes());
+    file.push(Kind::BoyerMoore as u8);
+    put_bytes(&mut file, b"A");
+    put_bytes(&mut file, &payload);
+    let checksum = fnv1a(&file);
+    put_u64(&mut file, checksum);
+    assert_eq!(io::ErrorKind::InvalidData, load::<BoyerMoore<u8>, _>(file.as_slice()).err().unwrap().kind());
+}
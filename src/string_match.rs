@@ -1,5 +1,110 @@
-use std::{collections::{HashMap, VecDeque}};
-use primes::PrimeSet;
+use std::{collections::{HashMap, VecDeque, hash_map::RandomState}, hash::{BuildHasher, Hash, Hasher}, fmt, io::Read};
+use indexmap::IndexSet;
+
+use crate::z_algorithm;
+
+/// A single symbol of a text or pattern, e.g. `u8` for raw FASTA bytes or `char` for unicode text.
+pub trait Symbol: Copy + Eq + Hash {}
+
+impl<T> Symbol for T where T: Copy + Eq + Hash {}
+
+/// Symbol with an integer code, so an [`Alphabet`] ranks it with a plain table lookup
+/// as long as the code is below [`DENSE`].
+pub trait DenseSymbol: Symbol {
+    fn code(self) -> usize;
+
+    /// The symbols as raw bytes, if they are bytes, for byte oriented fast paths.
+    fn as_bytes(_symbols: &[Self]) -> Option<&[u8]> {
+        None
+    }
+}
+
+/// Codes below this are looked up in flat tables, larger ones, e.g. of emoji, in maps.
+pub const DENSE: usize = 256;
+
+impl DenseSymbol for u8 {
+    fn code(self) -> usize {
+        self as usize
+    }
+
+    fn as_bytes(symbols: &[Self]) -> Option<&[u8]> {
+        Some(symbols)
+    }
+}
+
+impl DenseSymbol for u16 {
+    fn code(self) -> usize {
+        self as usize
+    }
+}
+
+impl DenseSymbol for u32 {
+    fn code(self) -> usize {
+        self as usize
+    }
+}
+
+impl DenseSymbol for usize {
+    fn code(self) -> usize {
+        self
+    }
+}
+
+impl DenseSymbol for char {
+    fn code(self) -> usize {
+        self as usize
+    }
+}
+
+/// Compressed alphabet of a pattern. Its symbols get the ranks 1..=σ in order of
+/// appearance and every other symbol shares the default rank 0.
+pub struct Alphabet<T>{
+    symbols: Vec<T>,
+    /// Ranks of the codes below [`DENSE`]
+    ranks: Vec<usize>,
+    /// Ranks of the symbols with larger codes
+    sparse: HashMap<T, usize>
+}
+
+impl<T> Alphabet<T> where T: DenseSymbol {
+    pub fn new(pattern: &[T]) -> Self {
+        let symbols: Vec<T> = pattern.iter().copied().collect::<IndexSet<T>>().into_iter().collect();
+        let mut ranks = vec![0; symbols.iter().map(|c| c.code()+1).filter(|len| *len <= DENSE).max().unwrap_or(0)];
+        let mut sparse = HashMap::new();
+        for (rank, c) in symbols.iter().enumerate() {
+            match c.code() {
+                code if code < DENSE => ranks[code] = rank+1,
+                _ => { sparse.insert(*c, rank+1); }
+            }
+        }
+        Self{
+            symbols,
+            ranks,
+            sparse
+        }
+    }
+
+    /// Number of ranks including the default one.
+    pub fn len(&self) -> usize {
+        self.symbols.len()+1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn rank(&self, symbol: T) -> usize {
+        match symbol.code() {
+            code if code < DENSE => *self.ranks.get(code).unwrap_or(&0),
+            _ => *self.sparse.get(&symbol).unwrap_or(&0)
+        }
+    }
+
+    /// Symbols of the alphabet, the symbol of rank `r` is at `r-1`.
+    pub fn symbols(&self) -> &[T] {
+        &self.symbols
+    }
+}
 
 fn str_match<T>(start: usize, text: &[T], pattern: &[T]) -> bool where T: Eq {
     // Memcompare is way faster
@@ -13,7 +118,7 @@ fn str_match<T>(start: usize, text: &[T], pattern: &[T]) -> bool where T: Eq {
     // true
 }
 
-pub fn naive_match<T,P>(text: T, pattern: P) -> Vec<usize> where T: AsRef<[char]>, P: AsRef<[char]> {
+pub fn naive_match<T,P,S>(text: T, pattern: P) -> Vec<usize> where T: AsRef<[S]>, P: AsRef<[S]>, S: Eq {
     let mut matches= vec![];
     let text = text.as_ref();
     let pattern = pattern.as_ref();
@@ -32,6 +137,40 @@ pub fn naive_match<T,P>(text: T, pattern: P) -> Vec<usize> where T: AsRef<[char]
     matches
 }
 
+/// Deterministic pseudo random text over `alphabet` for tests, from a linear congruential generator.
+#[cfg(test)]
+pub(crate) fn random_text<T>(seed: u32, len: usize, alphabet: &[T]) -> Vec<T> where T: Copy {
+    let mut state = seed;
+    (0..len).map(|_| {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        alphabet[(state >> 16) as usize % alphabet.len()]
+    }).collect()
+}
+
+/// [`naive_match`] as a [`TextMatch`], every window is compared symbol by symbol.
+pub struct Naive<T>{
+    pattern: Vec<T>
+}
+
+impl<T> Naive<T> where T: Symbol {
+    pub fn new(pattern: &[T]) -> Self {
+        Self{
+            pattern: pattern.to_vec()
+        }
+    }
+}
+
+impl<T> TextMatch<T> for Naive<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let m = self.pattern.len();
+        Box::new((0..(text.len()+1).saturating_sub(m)).filter(move |s| m > 0 && str_match(*s, text, self.pattern.as_slice())))
+    }
+}
+
 #[test]
 fn test_naive_matches() {
     let (pattern, text): (Vec<char>, Vec<char>) = ("sock".chars().collect(), "I am sock a sock".chars().collect());
@@ -46,138 +185,549 @@ fn test_naive_crash() {
     naive_match(text.as_slice(), pattern.as_slice());
 }
 
-pub struct RabinKarp{
-    trans_pattern: Vec<u32>,
-    sigma_map: HashMap<char, u32>,
-    d_base: u32,
-    q_prime: u32,
+/// Mersenne prime 2^61-1, the modulus of a random [`RollingHash`].
+pub const MERSENNE_61: u64 = (1 << 61) - 1;
+
+/// Polynomial rolling hash modulo a prime. Products are taken in u128,
+/// so neither long patterns nor large moduli overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RollingHash{
+    pub(crate) base: u64,
+    pub(crate) modulus: u64
 }
 
-impl RabinKarp{
-    pub fn new(pattern: &[char], sigma: &[char]) -> Self {
-        let sigma_map: HashMap<char, u32> = sigma.iter().enumerate().map(|(idx, char)| (*char, idx as u32)).collect();
-        let d_base = (sigma_map.len() - 1) as u32;
-        let (_, q_prime) = primes::Sieve::new().find(d_base as u64);
+impl RollingHash{
+    /// The modulus should be a prime, the larger the fewer spurious hits.
+    pub fn new(base: u64, modulus: u64) -> Self {
+        if !(2..=1 << 63).contains(&modulus) { panic!("modulus has to be in 2..=2^63"); }
         Self{
-            d_base,
-            trans_pattern: pattern.iter().map(|p| sigma_map[p]).collect(),
-            sigma_map,
-            q_prime: q_prime as u32
+            base: base % modulus,
+            modulus
         }
     }
-}
 
-impl TextMatch for RabinKarp {
-    fn match_text(&self, text: &[char]) -> Vec<usize> {
-        let mut matches= vec![];
+    /// Random base modulo [`MERSENNE_61`], so no fixed text can be full of collisions.
+    pub fn random() -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        Self::new(256 + seed % (MERSENNE_61 - 512), MERSENNE_61)
+    }
+
+    fn mul(&self, a: u64, b: u64) -> u64 {
+        ((a as u128 * b as u128) % self.modulus as u128) as u64
+    }
+
+    pub fn hash<I>(&self, values: I) -> u64 where I: IntoIterator<Item = u64> {
+        values.into_iter().fold(0, |h, v| (self.mul(h, self.base) + v % self.modulus) % self.modulus)
+    }
 
-        let m = self.trans_pattern.len();
+    /// Weight `base^(m-1)` of the symbol leaving a window of length `m`.
+    pub fn high(&self, m: usize) -> u64 {
+        (1..m).fold(1, |h, _| self.mul(h, self.base))
+    }
 
-        let trans_text: Vec<u32> = text.iter().map(|p| self.sigma_map[p]).collect();
+    /// Hash of the window moved by one symbol, `high` is [`RollingHash::high`] of the window length.
+    pub fn roll(&self, hash: u64, pop: u64, push: u64, high: u64) -> u64 {
+        let without = (hash + self.modulus - self.mul(pop % self.modulus, high)) % self.modulus;
+        (self.mul(without, self.base) + push % self.modulus) % self.modulus
+    }
+}
 
-        let h = self.d_base.pow(m as u32 - 1) % self.q_prime;
-        let mut p = 0;
-        let mut t_s = 0;
+pub struct RabinKarp<T>{
+    pub(crate) pattern: Vec<T>,
+    pub(crate) sigma_map: HashMap<T, u64>,
+    pub(crate) hashes: Vec<RollingHash>
+}
 
-        let mut buffer = VecDeque::<u32>::with_capacity(m+1);
+impl<T> RabinKarp<T> where T: Symbol {
+    /// Single random hash, see [`RollingHash::random`].
+    pub fn new(pattern: &[T], sigma: &[T]) -> Self {
+        Self::with_hashes(pattern, sigma, &[RollingHash::random()])
+    }
 
-        for (_i, (p_i, t_i)) in self.trans_pattern.iter().zip(trans_text.iter()).enumerate(){
-            p = (self.d_base * p + *p_i) % self.q_prime;
-            t_s = (self.d_base * t_s + *t_i) % self.q_prime;
-            buffer.push_back(*t_i);
+    /// A window is only compared to the pattern if all of the hashes agree,
+    /// two independent hashes make spurious hits practically impossible.
+    pub fn with_hashes(pattern: &[T], sigma: &[T], hashes: &[RollingHash]) -> Self {
+        Self{
+            pattern: pattern.to_vec(),
+            // 0 is left for symbols outside of sigma
+            sigma_map: sigma.iter().enumerate().map(|(idx, char)| (*char, idx as u64 + 1)).collect(),
+            hashes: hashes.to_vec()
         }
+    }
+}
+
+impl<T> TextMatch<T> for RabinKarp<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let (m, n) = (self.pattern.len(), text.len());
+        let value = move |c: &T| *self.sigma_map.get(c).unwrap_or(&0);
+
+        let p: Vec<u64> = self.hashes.iter().map(|h| h.hash(self.pattern.iter().map(value))).collect();
+        let high: Vec<u64> = self.hashes.iter().map(|h| h.high(m)).collect();
+        let mut t_s: Vec<u64> = self.hashes.iter().map(|h| h.hash(text.iter().take(m).map(value))).collect();
 
-        // println!("0: {:?}\n{}", buffer, t_s);
-        let mut iter = trans_text.iter().skip(m);
         let mut i = 0;
-        loop {
-            if t_s == p && str_match(i, trans_text.as_slice(), self.trans_pattern.as_slice()) {
-                matches.push(i);
-            }
-            if let (Some(pop), Some(push)) = (buffer.pop_front(), iter.next()) {
+        Box::new(std::iter::from_fn(move || {
+            while m > 0 && i+m <= n {
+                let s = i;
+                let hit = t_s == p && str_match(s, text, self.pattern.as_slice());
+                if let Some(push) = text.get(s+m) {
+                    for (k, h) in self.hashes.iter().enumerate() {
+                        t_s[k] = h.roll(t_s[k], value(&text[s]), value(push), high[k]);
+                    }
+                }
                 i += 1;
-                // println!("was: {}", t_s);
-                // println!("pop: {}",(pop as u32 * h) % q_prime);
-                // println!("push: {}",(push as u32) % q_prime);
-                let t_next = ((t_s as i32 - (pop * h) as i32) * self.d_base as i32 + *push as i32).rem_euclid(self.q_prime as i32);
-                // println!("is: {}", t_next);
-                t_s = t_next as u32;
-                buffer.push_back(*push);
-                // println!("{}: {:?}\n{}", i, buffer, t_s);
-            } else {
-                break;
+                if hit {
+                    return Some(s);
+                }
             }
-        }
-        matches
+            None
+        }))
     }
 }
 
-pub fn rabin_karp<T>(text: T, pattern: T, d_base: u32, q_prime: u32) -> Vec<usize> where T: AsRef<[char]> {
+/// Rabin-Karp straight on the symbol codes, every hash hit is verified.
+pub fn rabin_karp<T,S>(text: T, pattern: T, d_base: u64, q_prime: u64) -> Vec<usize> where T: AsRef<[S]>, S: Copy + Eq + Into<u32> {
     let mut matches= vec![];
 
     let text = text.as_ref();
     let pattern = pattern.as_ref();
+    let (m, n) = (pattern.len(), text.len());
+    if m == 0 || m > n {
+        return matches;
+    }
 
-    // let n = text.chars().count();
-    let m = pattern.len();
+    let hash = RollingHash::new(d_base, q_prime);
+    let value = |c: &S| (*c).into() as u64;
+    let h = hash.high(m);
+    let p = hash.hash(pattern.iter().map(value));
+    let mut t_s = hash.hash(text[..m].iter().map(value));
+
+    for i in 0..=n-m {
+        if t_s == p && str_match(i, text, pattern) {
+            matches.push(i);
+        }
+        if let Some(push) = text.get(i+m) {
+            t_s = hash.roll(t_s, value(&text[i]), value(push), h);
+        }
+    }
+    matches
+}
 
-    let h = d_base.pow(m as u32 - 1) % q_prime;
-    let mut p = 0;
-    let mut t_s = 0;
+#[test]
+fn test_rolling_hash() {
+    let hash = RollingHash::new(257, MERSENNE_61);
+    let values: Vec<u64> = b"GAATTCGGATCC".iter().map(|c| *c as u64).collect();
+    let h = hash.high(4);
+    let mut t_s = hash.hash(values[..4].iter().copied());
+    for i in 1..=values.len()-4 {
+        t_s = hash.roll(t_s, values[i-1], values[i+3], h);
+        assert_eq!(hash.hash(values[i..i+4].iter().copied()), t_s);
+    }
+}
+
+#[test]
+fn test_rabin_karp_verified() {
+    let text = b"TTATTATGAATTCTTATTATAAAAAAACGT";
+    // A tiny modulus lets nearly every window collide with the pattern
+    let expected = naive_match(&text[..], b"TTATTAT");
+    assert_eq!(expected, rabin_karp(&text[..], b"TTATTAT", 2, 3));
+    assert_eq!(expected, RabinKarp::with_hashes(b"TTATTAT", b"ACGT", &[RollingHash::new(4, 3)]).match_text(text));
+    assert_eq!(expected, RabinKarp::with_hashes(b"TTATTAT", b"ACGT", &[RollingHash::new(4, 3), RollingHash::random()]).match_text(text));
+    // Long patterns used to overflow base^(m-1) in u32
+    let pattern = b"ACGT".repeat(300);
+    let text = [b"TT".to_vec(), pattern.clone(), b"G".to_vec()].concat();
+    assert_eq!(vec![2], RabinKarp::new(pattern.as_slice(), b"ACGT").match_text(text.as_slice()));
+    assert_eq!(vec![2], rabin_karp(text.as_slice(), pattern.as_slice(), 256, MERSENNE_61));
+}
 
-    let mut buffer = VecDeque::<char>::with_capacity(m+1);
+/// Rabin-Karp for a set of patterns. Patterns are bucketed by length, every length gets
+/// its own rolling hash over the text and a hash hit is verified against each pattern in the bucket.
+pub struct MultiRabinKarp<T>{
+    patterns: Vec<Vec<T>>,
+    sigma_map: HashMap<T, u64>,
+    /// Per pattern length, the ids of the patterns with a given hash
+    buckets: Vec<(usize, HashMap<u64, Vec<usize>>)>,
+    hash: RollingHash
+}
 
-    for (_i, (p_i, t_i)) in pattern.iter().zip(text.iter()).enumerate(){
-        p = (d_base * p + *p_i as u32) % q_prime;
-        t_s = (d_base * t_s + *t_i as u32) % q_prime;
-        buffer.push_back(*t_i);
+impl<T> MultiRabinKarp<T> where T: Symbol {
+    pub fn new<P>(patterns: &[P]) -> Self where P: AsRef<[T]> {
+        Self::with_hash(patterns, RollingHash::random())
     }
 
-    // println!("0: {:?}\n{}", buffer, t_s);
-    let mut iter = text.iter().skip(m);
-    let mut i = 0;
-    loop {
-        if t_s == p {
-            // TODO actual string or u match
-            matches.push(i);
+    pub fn with_hash<P>(patterns: &[P], hash: RollingHash) -> Self where P: AsRef<[T]> {
+        let mut sigma_map = HashMap::new();
+        for c in patterns.iter().flat_map(|p| p.as_ref()) {
+            let next = sigma_map.len() as u64 + 1;
+            sigma_map.entry(*c).or_insert(next);
         }
-        if let (Some(pop), Some(push)) = (buffer.pop_front(),iter.next()) {
-            i += 1;
-            // println!("was: {}", t_s);
-            // println!("pop: {}",(pop as u32 * h) % q_prime);
-            // println!("push: {}",(push as u32) % q_prime);
-            let t_next = ((t_s as i32 - (pop as u32 * h) as i32) * d_base as i32 + (*push as u32) as i32).rem_euclid(q_prime as i32);
-            // println!("is: {}", t_next);
-            t_s = t_next as u32;
-            buffer.push_back(*push);
-            // println!("{}: {:?}\n{}", i, buffer, t_s);
-        } else {
-            break;
+        let mut buckets: Vec<(usize, HashMap<u64, Vec<usize>>)> = vec![];
+        for (id, pattern) in patterns.iter().enumerate() {
+            let pattern = pattern.as_ref();
+            let fingerprint = hash.hash(pattern.iter().map(|c| sigma_map[c]));
+            let bucket = match buckets.iter().position(|(m, _)| *m == pattern.len()) {
+                Some(idx) => idx,
+                None => {
+                    buckets.push((pattern.len(), HashMap::new()));
+                    buckets.len()-1
+                }
+            };
+            buckets[bucket].1.entry(fingerprint).or_default().push(id);
+        }
+        Self{
+            patterns: patterns.iter().map(|p| p.as_ref().to_vec()).collect(),
+            sigma_map,
+            buckets,
+            hash
         }
     }
-    matches
+
 }
 
-pub trait TextMatch{
-    fn match_text(&self, text: &[char]) -> Vec<usize>;
+impl<T> MultiMatch<T> for MultiRabinKarp<T> where T: Symbol {
+    fn pattern_lens(&self) -> Vec<usize> {
+        self.patterns.iter().map(|p| p.len()).collect()
+    }
+
+    fn match_patterns(&self, text: &[T]) -> Vec<(usize, usize)> {
+        let mut matches = vec![];
+        let value = |c: &T| *self.sigma_map.get(c).unwrap_or(&0);
+        for (m, hashes) in &self.buckets {
+            let m = *m;
+            if m == 0 || m > text.len() {
+                continue;
+            }
+            let h = self.hash.high(m);
+            let mut t_s = self.hash.hash(text[..m].iter().map(value));
+            for i in 0..=text.len()-m {
+                if let Some(ids) = hashes.get(&t_s) {
+                    for id in ids {
+                        if str_match(i, text, self.patterns[*id].as_slice()) {
+                            matches.push((*id, i));
+                        }
+                    }
+                }
+                if let Some(push) = text.get(i+m) {
+                    t_s = self.hash.roll(t_s, value(&text[i]), value(push), h);
+                }
+            }
+        }
+        matches.sort_unstable_by_key(|(id, pos)| (*pos, *id));
+        matches
+    }
 }
 
-pub struct Automat{
-    accept: usize,
-    transition_functions: HashMap<
-        u64, usize
-    >,
+#[test]
+fn test_multi_rabin_karp() {
+    let text = b"GAATTCGGATCCATTTAAATGAATTCAATTGGATCCC";
+    let patterns: [&[u8]; 6] = [b"GAATTC", b"GGATCC", b"ATTTAAAT", b"AATT", b"C", b"GGGG"];
+    let mut expected = vec![];
+    for (id, pattern) in patterns.iter().enumerate() {
+        expected.extend(naive_match(&text[..], pattern).into_iter().map(|pos| (id, pos)));
+    }
+    expected.sort_unstable_by_key(|(id, pos)| (*pos, *id));
+    assert_eq!(expected, MultiRabinKarp::new(&patterns).match_patterns(text));
+    assert_eq!(expected, MultiRabinKarp::with_hash(&patterns, RollingHash::new(4, 5)).match_patterns(text));
+    assert_eq!(AhoCorasick::new(&patterns).match_patterns(text), MultiRabinKarp::new(&patterns).match_patterns(text));
+}
+
+/// Which occurrences a search reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchSemantics{
+    /// Every occurrence, including those overlapping each other.
+    #[default]
+    Overlapping,
+    /// Scan left to right and skip past each reported occurrence, like `str::match_indices`.
+    /// Among patterns starting at the same position the one listed first wins.
+    NonOverlapping,
+    /// Like `NonOverlapping`, but the longest pattern starting at a position wins.
+    /// Identical to it for a single pattern.
+    LeftmostLongest
+}
+
+impl MatchSemantics{
+    /// Whether a match of an m symbol pattern at `pos` is reported, given the increasing
+    /// positions before it. `next` is where the following match may start at the earliest.
+    fn admits(self, pos: usize, m: usize, next: &mut usize) -> bool {
+        if self == MatchSemantics::Overlapping {
+            return true;
+        }
+        if pos < *next {
+            return false;
+        }
+        *next = pos + m.max(1);
+        true
+    }
+
+    /// Keep those of the increasing positions of an m symbol pattern this semantics reports.
+    pub fn select<I>(self, positions: I, m: usize) -> impl Iterator<Item = usize> where I: IntoIterator<Item = usize> {
+        let mut next = 0;
+        positions.into_iter().filter(move |pos| self.admits(*pos, m, &mut next))
+    }
+
+    /// Keep those of the matches this semantics reports, sorted by position. Matches starting
+    /// at the same position keep their order, e.g. by pattern id or strand, so the first one wins
+    /// unless `LeftmostLongest` takes a longer one.
+    pub fn select_matches(self, mut matches: Vec<Match>) -> Vec<Match> {
+        if self == MatchSemantics::Overlapping {
+            return matches;
+        }
+        matches.sort_by_key(|m| m.start);
+        let mut selected: Vec<Match> = vec![];
+        let mut next = 0;
+        for m in matches {
+            if m.start < next {
+                // A longer match at the position just taken replaces it
+                match selected.last_mut() {
+                    Some(last) if self == MatchSemantics::LeftmostLongest && last.start == m.start && m.end > last.end => {
+                        *last = m;
+                        next = m.end.max(m.start+1);
+                    },
+                    _ => ()
+                }
+                continue;
+            }
+            selected.push(m);
+            next = m.end.max(m.start+1);
+        }
+        selected
+    }
+
+    /// Keep those of the `(pattern_id, position)` pairs, sorted by position and pattern id,
+    /// this semantics reports.
+    pub fn select_patterns(self, matches: Vec<(usize, usize)>, lengths: &[usize]) -> Vec<(usize, usize)> {
+        let matches = matches.into_iter().map(|(id, pos)| Match::new(pos, pos+lengths[id]).with_pattern(id)).collect();
+        self.select_matches(matches).into_iter().map(|m| (m.pattern, m.start)).collect()
+    }
+}
+
+/// Strand of a double stranded sequence a hit was found on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Strand{
+    Forward,
+    Reverse
+}
+
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-")
+        }
+    }
+}
+
+/// A single occurrence, spanning `start..end` of the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Match{
+    pub start: usize,
+    pub end: usize,
+    /// Index of the pattern in a multi-pattern search
+    pub pattern: usize,
+    /// Index of the record, e.g. within a FASTA file, the text came from
+    pub record: usize,
+    pub strand: Strand,
+    /// Mismatches or edits of an approximate occurrence
+    pub score: Option<usize>
+}
+
+impl Match{
+    /// Exact forward occurrence of the first pattern in the first record.
+    pub fn new(start: usize, end: usize) -> Self {
+        Self{
+            start,
+            end,
+            pattern: 0,
+            record: 0,
+            strand: Strand::Forward,
+            score: None
+        }
+    }
+
+    pub fn with_pattern(self, pattern: usize) -> Self {
+        Self{ pattern, ..self }
+    }
+
+    pub fn with_record(self, record: usize) -> Self {
+        Self{ record, ..self }
+    }
+
+    pub fn with_strand(self, strand: Strand) -> Self {
+        Self{ strand, ..self }
+    }
+
+    pub fn with_score(self, score: usize) -> Self {
+        Self{ score: Some(score), ..self }
+    }
+}
+
+pub trait TextMatch<T>{
+    /// Lazily yield the positions of all matches in increasing order.
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a>;
+
+    /// Number of text symbols an occurrence spans.
+    fn pattern_len(&self) -> usize;
+
+    fn match_text(&self, text: &[T]) -> Vec<usize> {
+        self.find_iter(text).collect()
+    }
+
+    /// All matches with their spans.
+    fn find_matches(&self, text: &[T]) -> Vec<Match> {
+        let m = self.pattern_len();
+        self.find_iter(text).map(|s| Match::new(s, s+m)).collect()
+    }
+
+    /// Lazily yield the positions of the matches reported under `semantics`.
+    fn find_iter_with<'a>(&'a self, text: &'a [T], semantics: MatchSemantics) -> Box<dyn Iterator<Item = usize> + 'a> {
+        Box::new(semantics.select(self.find_iter(text), self.pattern_len()))
+    }
+
+    fn match_text_with(&self, text: &[T], semantics: MatchSemantics) -> Vec<usize> {
+        self.find_iter_with(text, semantics).collect()
+    }
+
+    /// Position of the leftmost match, the search stops right there.
+    fn find_first(&self, text: &[T]) -> Option<usize> {
+        self.find_iter(text).next()
+    }
+
+    /// Number of matches without collecting their positions.
+    fn count(&self, text: &[T]) -> usize {
+        self.find_iter(text).count()
+    }
+
+    fn is_match(&self, text: &[T]) -> bool {
+        self.find_first(text).is_some()
+    }
+}
+
+/// Matcher for a set of patterns, each identified by its index in the set.
+pub trait MultiMatch<T>{
+    /// Length of every pattern by id.
+    fn pattern_lens(&self) -> Vec<usize>;
+
+    /// All occurrences as `(pattern_id, position)`, sorted by position and pattern id.
+    fn match_patterns(&self, text: &[T]) -> Vec<(usize, usize)>;
+
+    /// Occurrences reported under `semantics`, sorted by position and pattern id.
+    fn match_patterns_with(&self, text: &[T], semantics: MatchSemantics) -> Vec<(usize, usize)> {
+        semantics.select_patterns(self.match_patterns(text), self.pattern_lens().as_slice())
+    }
+
+    /// All occurrences with their spans and pattern ids, sorted by position and pattern id.
+    fn find_matches(&self, text: &[T]) -> Vec<Match> {
+        let lengths = self.pattern_lens();
+        self.match_patterns(text).into_iter().map(|(id, s)| Match::new(s, s+lengths[id]).with_pattern(id)).collect()
+    }
+}
+
+/// Matcher that consumes its text chunk by chunk, e.g. while reading a file or a pipe.
+/// Positions are reported relative to the start of the whole stream and a match
+/// straddling two chunks is reported exactly once. Every overlapping match is reported,
+/// [`StreamMatch::with_semantics`] selects others.
+pub trait StreamMatch<T>{
+    fn pattern_len(&self) -> usize;
+    /// Consume the next chunk and return the matches completed within it.
+    fn feed(&mut self, chunk: &[T]) -> Vec<usize>;
+    /// Number of symbols consumed so far.
+    fn offset(&self) -> usize;
+    /// Forget everything seen so far and start a new stream.
+    fn reset(&mut self);
+
+    /// Report the matches selected by `semantics`, across chunk borders as well.
+    fn with_semantics(self, semantics: MatchSemantics) -> SelectStream<Self> where Self: Sized {
+        SelectStream{
+            matcher: self,
+            semantics,
+            next: 0
+        }
+    }
+}
+
+/// A stream matcher reporting only the matches of its [`MatchSemantics`].
+pub struct SelectStream<M>{
+    matcher: M,
+    semantics: MatchSemantics,
+    /// Stream position the next reported match may start at
+    next: usize
+}
+
+impl<T, M> StreamMatch<T> for SelectStream<M> where M: StreamMatch<T> {
+    fn pattern_len(&self) -> usize {
+        self.matcher.pattern_len()
+    }
+
+    fn feed(&mut self, chunk: &[T]) -> Vec<usize> {
+        let m = self.matcher.pattern_len();
+        let (semantics, next) = (self.semantics, &mut self.next);
+        self.matcher.feed(chunk).into_iter().filter(|pos| semantics.admits(*pos, m, next)).collect()
+    }
+
+    fn offset(&self) -> usize {
+        self.matcher.offset()
+    }
+
+    fn reset(&mut self) {
+        self.matcher.reset();
+        self.next = 0;
+    }
+}
+
+/// Run a stream matcher over everything `reader` yields, reading `chunk_size` bytes at a time.
+/// The bytes are matched as they are, use [`match_fasta`] for FASTA files. Wrap the matcher
+/// with [`StreamMatch::with_semantics`] to report other than overlapping matches.
+pub fn match_reader<R, M>(mut reader: R, matcher: &mut M, chunk_size: usize) -> std::io::Result<Vec<usize>> where R: Read, M: StreamMatch<u8> + ?Sized {
+    let mut matches = vec![];
+    let mut buffer = vec![0; chunk_size.max(1)];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => matches.extend(matcher.feed(&buffer[..read])),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e)
+        }
+    }
+    Ok(matches)
 }
 
-impl Automat{
-    pub fn prep(u: usize, c: char) -> u64 {
-        u as u64 + ((c as u64) << 32)
+/// Run a stream matcher over the sequence of every record of a FASTA file. Headers and line
+/// breaks are skipped, so a match may wrap a line. Each match carries the index of its record
+/// and is positioned within the sequence of that record.
+pub fn match_fasta<R, M>(reader: R, matcher: &mut M) -> std::io::Result<Vec<Match>> where R: Read, M: StreamMatch<u8> + ?Sized {
+    let mut reader = seq_io::fasta::Reader::new(reader);
+    let mut matches = vec![];
+    let mut record = 0;
+    while let Some(result) = reader.next() {
+        let entry = result.map_err(|e| match e {
+            seq_io::fasta::Error::Io(e) => e,
+            e => std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+        })?;
+        matcher.reset();
+        let m = matcher.pattern_len();
+        for line in entry.seq_lines() {
+            matches.extend(matcher.feed(line).into_iter().map(|s| Match::new(s, s+m).with_record(record)));
+        }
+        record += 1;
     }
+    Ok(matches)
+}
+
+pub struct Automat<T>{
+    pub(crate) accept: usize,
+    pub(crate) transition_functions: HashMap<
+        (usize, T), usize
+    >,
+}
 
-    pub fn new(pattern: &[char]) -> Self{
-        let mut alphabet = pattern.to_vec();
-        alphabet.sort_unstable();
-        alphabet.dedup();
+impl<T> Automat<T> where T: Symbol {
+    pub fn new(pattern: &[T]) -> Self{
+        let alphabet: IndexSet<T> = pattern.iter().copied().collect();
+        let pi = z_algorithm::prefix_function(pattern);
         let mut transition_functions = 
         // HashMap::with_capacity_and_hasher(alphabet.len() * pattern.len()+1, BuildHasherDefault::default());
         //IntMap::default();
@@ -187,22 +737,20 @@ impl Automat{
             for a in &alphabet {
                 if idx < pattern.len() && pattern[idx] == *a {
                     transition_functions.insert(
-                        Automat::prep(idx, *a),
+                        (idx, *a),
                         idx+1
                     );
-                } else {
+                } else if idx > 0 {
                     // println!("({}, {}): {}", idx, a, &pattern[0..idx].iter().collect::<String>());
-                    // have we seen the pattern before?
-                    // What we seen so far...
-                    let slice = [&pattern[0..idx], &[*a]].concat();
-                    let pos = Automat::sigma_suffix(slice.as_slice());
+                    // A mismatch continues like it would from the longest border of what we
+                    // have seen so far, whose transitions are already known since pi[idx] < idx.
+                    let pos = *transition_functions.get(&(pi[idx], *a)).unwrap_or(&0);
                     if pos != 0 {
                         transition_functions.insert(
-                            Automat::prep(idx, *a),
+                            (idx, *a),
                             pos
                         );
                     }
-                    // println!("next: {}\nslice: {}\nposition: {}", a, slice.iter().collect::<String>(), pos);
                 }
             }
         }
@@ -214,7 +762,8 @@ impl Automat{
     }
 
     /// Return the length of the longest prefix that is also a suffix
-    fn sigma_suffix(x: &[char]) -> usize{
+    #[cfg(test)]
+    fn sigma_suffix(x: &[T]) -> usize{
         //P_k is a prefix of P of the length k
         for k in 1..x.len() {
             let prefix = &x[0..x.len()-k];
@@ -232,20 +781,72 @@ impl Automat{
     }
 }
 
-impl TextMatch for Automat {
-    fn match_text(&self, text: &[char]) -> Vec<usize> {
-        let mut matches = vec![];
+impl<T> Automat<T> where T: Symbol {
+    fn step(&self, state: usize, symbol: T) -> usize {
+        *self.transition_functions.get(&(state, symbol)).unwrap_or(&0)
+    }
+
+    pub fn stream(&self) -> AutomatStream<'_, T> {
+        AutomatStream{
+            automat: self,
+            state: 0,
+            offset: 0
+        }
+    }
+}
+
+impl<T> TextMatch<T> for Automat<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.accept
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
         let mut start = 0;
-        for (idx, char) in text.iter().enumerate(){
+        Box::new(text.iter().enumerate().filter_map(move |(idx, char)| {
             // start = *self.transitions[start].get(char).unwrap_or(&0);
             // self.transition_functions[&(start, *char)];
-            start = *self.transition_functions.get(&Automat::prep(start, *char)).unwrap_or(&0);
-            if start == self.accept {
-                matches.push(idx-self.accept+1);
+            start = self.step(start, *char);
+            if start == self.accept && self.accept > 0 {
+                Some(idx+1-self.accept)
+            } else {
+                None
+            }
+        }))
+    }
+}
+
+/// Streaming state of an [`Automat`], the automaton state carries over between chunks.
+pub struct AutomatStream<'a, T>{
+    automat: &'a Automat<T>,
+    state: usize,
+    offset: usize
+}
+
+impl<'a, T> StreamMatch<T> for AutomatStream<'a, T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.automat.accept
+    }
+
+    fn feed(&mut self, chunk: &[T]) -> Vec<usize> {
+        let mut matches = vec![];
+        for char in chunk {
+            self.state = self.automat.step(self.state, *char);
+            self.offset += 1;
+            if self.state == self.automat.accept && self.automat.accept > 0 {
+                matches.push(self.offset-self.automat.accept);
             }
         }
         matches
     }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn reset(&mut self) {
+        self.state = 0;
+        self.offset = 0;
+    }
 }
 
 #[test]
@@ -257,6 +858,23 @@ fn test_sigma() {
     assert_eq!(1, func(&['a','b','a']));
 }
 
+#[test]
+fn test_automat_transitions() {
+    // Quadratic construction straight from the definition
+    let pattern = "ababbabbababbababbabb".chars().collect::<Vec<char>>();
+    let mut expected = HashMap::new();
+    for idx in 0..=pattern.len() {
+        for a in ['a', 'b'] {
+            let slice = [&pattern[0..idx], &[a]].concat();
+            let next = if idx < pattern.len() && pattern[idx] == a { idx+1 } else { Automat::sigma_suffix(slice.as_slice()) };
+            if next != 0 {
+                expected.insert((idx, a), next);
+            }
+        }
+    }
+    assert_eq!(expected, Automat::new(pattern.as_slice()).transition_functions);
+}
+
 #[test]
 fn test_automat() {
     // Automat::new(&"ababbabbababb".chars().collect());
@@ -266,63 +884,267 @@ fn test_automat() {
     assert_eq!(vec![1, 9],result);
 }
 
-pub struct KnuthMorrisPratt{
-    pattern: Vec<char>,
-    pi: HashMap<usize, usize>
+/// String matching automaton with a dense transition table indexed by `state * |Σ| + rank`.
+/// Matching is a plain array walk, but the table needs (m+1)·(σ+1) entries, so for huge
+/// alphabets the sparse [`Automat`] is the better choice.
+pub struct DenseAutomat<T>{
+    alphabet: Alphabet<T>,
+    table: Vec<usize>,
+    accept: usize
 }
 
-impl KnuthMorrisPratt{
-    pub fn new(pattern: &[char]) -> Self{
-        let pi = compute_prefix(pattern);
-        // println!("{:?}", pi);
+impl<T> DenseAutomat<T> where T: DenseSymbol {
+    pub fn new(pattern: &[T]) -> Self {
+        let alphabet = Alphabet::new(pattern);
+        let sigma = alphabet.len();
+        let m = pattern.len();
+        let pi = z_algorithm::prefix_function(pattern);
+        let mut table = vec![0; (m+1) * sigma];
+        for idx in 0..=m {
+            // Rank 0 stays the default column that always returns to the start state
+            for rank in 1..sigma {
+                table[idx*sigma + rank] = if idx < m && alphabet.rank(pattern[idx]) == rank {
+                    idx+1
+                } else if idx > 0 {
+                    table[pi[idx]*sigma + rank]
+                } else {
+                    0
+                };
+            }
+        }
         Self{
-            pattern: pattern.iter().map(|c| *c).collect(),
-            pi
+            alphabet,
+            table,
+            accept: m
         }
     }
-}
 
-fn compute_prefix(pattern: &[char]) -> HashMap<usize, usize> {
-    let mut pi = HashMap::new();
-    for k in 0..pattern.len() {
-        let to = pattern.len()-k;
-        let slice = &pattern[0..to];
-        let pos = Automat::sigma_suffix(slice);
-        // println!("k{}, {:?}: {}", to, slice, pos);
-        pi.insert(to, pos);
+    /// Transition from `state` on `symbol`, a single table lookup.
+    fn step(&self, state: usize, symbol: T) -> usize {
+        self.table[state*self.alphabet.len() + self.alphabet.rank(symbol)]
+    }
+
+    fn accepts(&self, state: usize) -> bool {
+        state == self.accept && self.accept > 0
     }
-    pi
 }
 
-impl TextMatch for KnuthMorrisPratt {
-    fn match_text(&self, text: &[char]) -> Vec<usize> {
-        let mut matches = vec![];
-        let m = self.pattern.len();
-        let n = text.len();
-        let mut s = 0;
-        let mut q = 1;
-        while s < n-m {
-            // println!("s {} q{}, {:?}", s, q, &text[s..s+q]);
-            if text[s+q] != self.pattern[q] {
-                if None == self.pi.get(&q) {
-                    println!("Crashed at s:{} q:{} pi:{:?}", s, q, self.pi);
-                }
-                let k = self.pi[&q];
-                s += q-k;
-                q = k;
+impl<T> TextMatch<T> for DenseAutomat<T> where T: DenseSymbol {
+    fn pattern_len(&self) -> usize {
+        self.accept
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let mut state = 0;
+        Box::new(text.iter().enumerate().filter_map(move |(idx, char)| {
+            state = self.step(state, *char);
+            if self.accepts(state) {
+                Some(idx+1-self.accept)
             } else {
+                None
+            }
+        }))
+    }
+}
+
+#[test]
+fn test_dense_automat() {
+    let pattern = "aabab".chars().collect::<Vec<char>>();
+    let text = "aaababaabaababaab".chars().collect::<Vec<char>>();
+    assert_eq!(vec![1, 9], DenseAutomat::new(pattern.as_slice()).match_text(text.as_slice()));
+    let text = b"TTATTATTATTATGAATTCTTATTATNNTTATTAT";
+    assert_eq!(Automat::new(b"TTATTAT").match_text(text), DenseAutomat::new(b"TTATTAT").match_text(text));
+    let alphabet = Alphabet::new(b"TTAG");
+    assert_eq!((4, 1, 2, 3, 0), (alphabet.len(), alphabet.rank(b'T'), alphabet.rank(b'A'), alphabet.rank(b'G'), alphabet.rank(b'C')));
+    // Large codes are ranked through the map, the table stays small
+    let text = "🧬a🧬a🧬".chars().collect::<Vec<char>>();
+    assert_eq!(vec![1], DenseAutomat::new(&['a', '🧬', 'a']).match_text(text.as_slice()));
+    assert_eq!(vec![0], DenseAutomat::new(&[usize::MAX-1]).match_text(&[usize::MAX-1, 3]));
+    assert_eq!((3, 2, 0), (Alphabet::new(&['🧬', 'a']).len(), Alphabet::new(&['🧬', 'a']).rank('a'), Alphabet::new(&['🧬', 'a']).rank('🦀')));
+}
+
+#[test]
+fn test_automata_empty_pattern() {
+    assert!(Automat::new(b"").match_text(b"ACG").is_empty());
+    assert!(Automat::new(b"").stream().feed(b"ACG").is_empty());
+    assert!(DenseAutomat::new(b"").match_text(b"ACG").is_empty());
+    let mut rt = RealTimeKmp::new(b"");
+    assert!(rt.match_text(b"ACG").is_empty());
+    assert!(rt.feed(b"ACG").is_empty());
+}
+
+pub struct KnuthMorrisPratt<T>{
+    pub(crate) pattern: Vec<T>,
+    /// `pi[q]` is the longest proper border of the first q pattern symbols, for q in 0..=m
+    pub(crate) pi: Vec<usize>
+}
+
+impl<T> KnuthMorrisPratt<T> where T: Symbol {
+    pub fn new(pattern: &[T]) -> Self{
+        let pi = z_algorithm::prefix_function(pattern);
+        // println!("{:?}", pi);
+        Self{
+            pattern: pattern.to_vec(),
+            pi
+        }
+    }
+}
+
+impl<T> KnuthMorrisPratt<T> where T: Symbol {
+    pub fn stream(&self) -> KnuthMorrisPrattStream<'_, T> {
+        KnuthMorrisPrattStream{
+            kmp: self,
+            q: 0,
+            offset: 0
+        }
+    }
+}
+
+impl<T> TextMatch<T> for KnuthMorrisPratt<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let m = self.pattern.len();
+        // q symbols of the pattern match the text read so far
+        let mut q = 0;
+        Box::new(text.iter().enumerate().filter_map(move |(idx, char)| {
+            if m == 0 {
+                return None;
+            }
+            while q > 0 && self.pattern[q] != *char {
+                q = self.pi[q];
+            }
+            if self.pattern[q] == *char {
                 q += 1;
-                if q == self.pattern.len() {
-                    matches.push(s);
+            }
+            if q == m {
+                q = self.pi[m];
+                Some(idx+1-m)
+            } else {
+                None
+            }
+        }))
+    }
+}
 
-                    let k = self.pi[&q];
-                    s += q-k;
-                    q = k;
-                }
+/// Streaming state of a [`KnuthMorrisPratt`] matcher, `q` symbols of the pattern are matched so far.
+pub struct KnuthMorrisPrattStream<'a, T>{
+    kmp: &'a KnuthMorrisPratt<T>,
+    q: usize,
+    offset: usize
+}
+
+impl<'a, T> StreamMatch<T> for KnuthMorrisPrattStream<'a, T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.kmp.pattern.len()
+    }
+
+    fn feed(&mut self, chunk: &[T]) -> Vec<usize> {
+        let mut matches = vec![];
+        let pattern = &self.kmp.pattern;
+        let m = pattern.len();
+        if m == 0 {
+            self.offset += chunk.len();
+            return matches;
+        }
+        for char in chunk {
+            while self.q > 0 && pattern[self.q] != *char {
+                self.q = self.kmp.pi[self.q];
+            }
+            if pattern[self.q] == *char {
+                self.q += 1;
+            }
+            self.offset += 1;
+            if self.q == m {
+                matches.push(self.offset-m);
+                self.q = self.kmp.pi[m];
             }
         }
         matches
     }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn reset(&mut self) {
+        self.q = 0;
+        self.offset = 0;
+    }
+}
+
+/// Real-time Knuth-Morris-Pratt for live input. The failure links are expanded ahead of time
+/// into a [`DenseAutomat`], so every symbol costs exactly one table lookup instead of an
+/// amortised chain of failure links, and no symbol waits longer than that for its answer.
+pub struct RealTimeKmp<T>{
+    automat: DenseAutomat<T>,
+    state: usize,
+    offset: usize
+}
+
+impl<T> RealTimeKmp<T> where T: DenseSymbol {
+    pub fn new(pattern: &[T]) -> Self {
+        Self{
+            automat: DenseAutomat::new(pattern),
+            state: 0,
+            offset: 0
+        }
+    }
+
+    /// Consume a single symbol, returning the start of the match it completes.
+    pub fn push(&mut self, symbol: T) -> Option<usize> {
+        self.state = self.automat.step(self.state, symbol);
+        self.offset += 1;
+        if self.automat.accepts(self.state) {
+            Some(self.offset-self.automat.accept)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> StreamMatch<T> for RealTimeKmp<T> where T: DenseSymbol {
+    fn pattern_len(&self) -> usize {
+        self.automat.accept
+    }
+
+    fn feed(&mut self, chunk: &[T]) -> Vec<usize> {
+        chunk.iter().filter_map(|c| self.push(*c)).collect()
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn reset(&mut self) {
+        self.state = 0;
+        self.offset = 0;
+    }
+}
+
+impl<T> TextMatch<T> for RealTimeKmp<T> where T: DenseSymbol {
+    fn pattern_len(&self) -> usize {
+        self.automat.accept
+    }
+
+    /// A whole text is searched by the underlying [`DenseAutomat`], the real-time variant
+    /// only differs from it on live input.
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        self.automat.find_iter(text)
+    }
+}
+
+#[test]
+fn test_real_time_kmp() {
+    let text = b"TTATTATTATTATGAATTCTTATTAT";
+    let expected = KnuthMorrisPratt::new(b"TTATTAT").match_text(text);
+    let mut rt = RealTimeKmp::new(b"TTATTAT");
+    assert_eq!(expected, text.iter().filter_map(|c| rt.push(*c)).collect::<Vec<usize>>());
+    rt.reset();
+    assert_eq!(expected, text.chunks(4).flat_map(|chunk| rt.feed(chunk)).collect::<Vec<usize>>());
+    assert_eq!(expected, rt.match_text(text));
 }
 
 #[test]
@@ -339,10 +1161,22 @@ fn test_knut() {
         "abab".chars().collect::<Vec<char>>().as_slice()
     );
 
-    abab.match_text("aaababaabaababaab".chars().collect::<Vec<char>>().as_slice());
+    let result = abab.match_text("aaababaabaababaab".chars().collect::<Vec<char>>().as_slice());
+    assert_eq!(vec![2, 10], result);
 }
 
-fn last_occurancies(pattern: &[char]) -> HashMap<char, usize> {
+#[test]
+fn test_knut_boundaries() {
+    let kmp = KnuthMorrisPratt::new(b"aab");
+    assert_eq!(vec![0, 5], kmp.match_text(b"aabaaaab"));
+    assert_eq!(vec![0], kmp.match_text(b"aab"));
+    assert_eq!(Vec::<usize>::new(), kmp.match_text(b"aa"));
+    assert_eq!(Vec::<usize>::new(), kmp.match_text(b""));
+    assert_eq!(vec![0, 1, 3], KnuthMorrisPratt::new(b"a").match_text(b"aaba"));
+    assert_eq!(Vec::<usize>::new(), KnuthMorrisPratt::new(b"").match_text(b"aaba"));
+}
+
+fn last_occurancies<T>(pattern: &[T]) -> HashMap<T, usize> where T: Symbol {
     let mut map = HashMap::new();
     for (idx, char) in pattern.iter().enumerate() {
         map.insert(*char, idx);
@@ -350,39 +1184,40 @@ fn last_occurancies(pattern: &[char]) -> HashMap<char, usize> {
     map
 }
 
-fn good_suffix(pattern: &[char]) -> Vec<usize> {
-    let m = pattern.len()-1;
-    let pi = compute_prefix(pattern);
-    let pattern_ = pattern.iter().copied().rev().collect::<Vec<char>>();
-    let pi_ = compute_prefix(pattern_.as_slice());
-    let mut lambda = vec![m - pi[&m]; m];
-    for l in 1..=m {
-        // println!("l:{}, m:{}",l, m);
-        let pi_off = pi_[&l];
-        let j = m-pi_off-1;
-        let next = l - pi_off;
-        if lambda[j] > next {
-            lambda[j] = next;
-        } 
-    }
-    // println!(
-    //     "lambda:{:?}\npi:{:?}\npi_:{:?}\nm:{}",
-    //     lambda.iter().map(|i| format!("{}", i)).collect::<String>(),
-    //     pi,
-    //     pi_,
-    //     m
-    // );
-    lambda
-}
-
-pub struct BoyerMoore{
-    last_occurancies: HashMap<char, usize>,
-    good_suffix: Vec<usize>,
-    pattern: Vec<char>
-}
-
-impl BoyerMoore{
-    pub fn new(pattern: &[char]) -> Self {
+/// Strong good suffix rule, `shift[j]` is the shift after `pattern[j+1..]` matched and
+/// `pattern[j]` did not. `shift[0]` is the period, the shift after a full match as well.
+fn good_suffix<T>(pattern: &[T]) -> Vec<usize> where T: Symbol {
+    let m = pattern.len();
+    // suffix[i] is the longest common suffix of pattern[..=i] and the pattern
+    let suffix = z_algorithm::n_array(pattern);
+    let mut shift = vec![m; m];
+    // The matched suffix contains a border of the pattern
+    let mut j = 0;
+    for i in (0..m).rev() {
+        if suffix[i] == i+1 {
+            while j < m-1-i {
+                if shift[j] == m {
+                    shift[j] = m-1-i;
+                }
+                j += 1;
+            }
+        }
+    }
+    // The matched suffix reoccurs, preceded by another symbol
+    for i in 0..m.saturating_sub(1) {
+        shift[m-1-suffix[i]] = m-1-i;
+    }
+    shift
+}
+
+pub struct BoyerMoore<T>{
+    pub(crate) last_occurancies: HashMap<T, usize>,
+    pub(crate) good_suffix: Vec<usize>,
+    pub(crate) pattern: Vec<T>
+}
+
+impl<T> BoyerMoore<T> where T: Symbol {
+    pub fn new(pattern: &[T]) -> Self {
         Self{
             last_occurancies: last_occurancies(pattern),
             good_suffix: good_suffix(pattern),
@@ -391,87 +1226,110 @@ impl BoyerMoore{
     } 
 }
 
-impl TextMatch for BoyerMoore {
-    fn match_text(&self, text: &[char]) -> Vec<usize> {
-        let mut matches = vec![];
+impl<T> BoyerMoore<T> where T: Symbol {
+    pub fn stream(&self) -> BoyerMooreStream<'_, T> {
+        BoyerMooreStream{
+            bm: self,
+            buffer: Vec::with_capacity(self.pattern.len()),
+            start: 0,
+            next: 0,
+            offset: 0
+        }
+    }
+
+    /// Compare the pattern right to left against the m symbol `window`, returns whether
+    /// it matched and how far the pattern shifts afterwards.
+    fn align(&self, window: &[T]) -> (bool, usize) {
+        // j symbols are left unmatched
+        let mut j = self.pattern.len();
+        while j > 0 && self.pattern[j-1] == window[j-1] {
+            j -= 1;
+        }
+        if j == 0 {
+            return (true, self.good_suffix[0]);
+        }
+        let j = j-1;
+        // Bad character rule, align the rightmost occurrence of the mismatched symbol
+        let check = self.last_occurancies.get(&window[j]).map_or(j+1, |oc| j.saturating_sub(*oc));
+        (false, self.good_suffix[j].max(check))
+    }
+}
+
+impl<T> TextMatch<T> for BoyerMoore<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
         let (mut s, m, n) = (0, self.pattern.len(), text.len());
 
-        while s < n-m+1 {
-            let mut j = m-1;
-            let mut t = text[s+j];
-            while j > 0 && self.pattern[j] == t {
-                j -= 1;
-                t = text[s+j];
+        Box::new(std::iter::from_fn(move || {
+            while m > 0 && s+m <= n {
+                let (found, shift) = self.align(&text[s..s+m]);
+                let pos = s;
+                s += shift;
+                if found {
+                    return Some(pos);
+                }
             }
-            // println!(
-            //     "(s,j,t):({},{},{})\n{}{}{}{}\n{}{}",
-            //     s,j,t,
-            //     &text[0..s].iter().collect::<String>(),
-            //     &text[s..s+j+1].iter().collect::<String>().as_str().red(),
-            //     &text[s+j+1..s+m].iter().collect::<String>().as_str().green(),
-            //     &text[s+m..n].iter().collect::<String>(),
-            //     vec![' ';s].iter().collect::<String>(),
-            //     &pattern.iter().collect::<String>().as_str().blue()
-            // );
-            s += if j == 0 {
-                // println!("Whoa...");
-                matches.push(s);
-                self.good_suffix[0]
-            } else {
-                // println!("t:{}, j:{}, l_occ:{}, g_s:{}", t, j, last_occurancies[&t], good_suffix[j-1]);
-                let check = (self.last_occurancies.get(&t).and_then(|
-                    oc| (oc+1).checked_sub(j))).unwrap_or_default();
-                // println!("check: {}", check);
-                (self.good_suffix[j-1]).max(check)
-            };
-            // println!("shift to: {}",s);
-            // return matches;
-        }
-        matches
+            None
+        }))
     }
 }
 
-pub fn boyer_moore(text: &[char], pattern: &[char]) -> Vec<usize> {
-    let mut matches = vec![];
-    let (mut s, m, n) = (0, pattern.len(), text.len());
+/// Streaming state of a [`BoyerMoore`] matcher. The alignment carries over chunk borders,
+/// so every window is compared once and the shifts skip symbols just like on a whole text.
+pub struct BoyerMooreStream<'a, T>{
+    bm: &'a BoyerMoore<T>,
+    /// The symbols from stream position `start` on, earlier ones are never compared again
+    buffer: Vec<T>,
+    start: usize,
+    /// Stream position of the next alignment of the pattern
+    next: usize,
+    offset: usize
+}
 
-    let last_occurancies = last_occurancies(pattern);
-    let good_suffix = good_suffix(pattern);
+impl<'a, T> StreamMatch<T> for BoyerMooreStream<'a, T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.bm.pattern.len()
+    }
 
-    // println!("lo: {:?}, gs:{:?}", last_occurancies, good_suffix);
+    fn feed(&mut self, chunk: &[T]) -> Vec<usize> {
+        let m = self.bm.pattern.len();
+        let mut matches = vec![];
+        self.buffer.extend_from_slice(chunk);
+        self.offset += chunk.len();
+        while m > 0 && self.next+m <= self.offset {
+            let s = self.next-self.start;
+            let (found, shift) = self.bm.align(&self.buffer[s..s+m]);
+            if found {
+                matches.push(self.next);
+            }
+            self.next += shift;
+        }
+        // Drop the dead prefix once it makes up half the buffer, amortised constant per symbol
+        let dead = self.next.min(self.offset)-self.start;
+        if dead > 0 && 2*dead >= self.buffer.len() {
+            self.buffer.drain(..dead);
+            self.start += dead;
+        }
+        matches
+    }
 
-    while s < n-m+1 {
-        let mut j = m-1;
-        let mut t = text[s+j];
-        while j > 0 && pattern[j] == t {
-            j -= 1;
-            t = text[s+j];
-        }
-        // println!(
-        //     "(s,j,t):({},{},{})\n{}{}{}{}\n{}{}",
-        //     s,j,t,
-        //     &text[0..s].iter().collect::<String>(),
-        //     &text[s..s+j+1].iter().collect::<String>().as_str().red(),
-        //     &text[s+j+1..s+m].iter().collect::<String>().as_str().green(),
-        //     &text[s+m..n].iter().collect::<String>(),
-        //     vec![' ';s].iter().collect::<String>(),
-        //     &pattern.iter().collect::<String>().as_str().blue()
-        // );
-        s += if j == 0 {
-            // println!("Whoa...");
-            matches.push(s);
-            good_suffix[0]
-        } else {
-            // println!("t:{}, j:{}, l_occ:{}, g_s:{}", t, j, last_occurancies[&t], good_suffix[j-1]);
-            let check = (last_occurancies.get(&t).and_then(|
-                oc| (oc+1).checked_sub(j))).unwrap_or_default();
-            // println!("check: {}", check);
-            (good_suffix[j-1]).max(check)
-        };
-        // println!("shift to: {}",s);
-        // return matches;
+    fn offset(&self) -> usize {
+        self.offset
     }
-    matches
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.start = 0;
+        self.next = 0;
+        self.offset = 0;
+    }
+}
+
+pub fn boyer_moore<T>(text: &[T], pattern: &[T]) -> Vec<usize> where T: Symbol {
+    BoyerMoore::new(pattern).match_text(text)
 }
 
 #[test]
@@ -506,3 +1364,572 @@ fn test_boyer_moore_ueb1() {
     );
     assert_eq!(vec![7], result);
 }
+
+/// Boyer-Moore-Horspool, only the bad character rule on the last symbol of the window.
+pub struct Horspool<T>{
+    shift: HashMap<T, usize>,
+    pattern: Vec<T>
+}
+
+impl<T> Horspool<T> where T: Symbol {
+    pub fn new(pattern: &[T]) -> Self {
+        let m = pattern.len();
+        Self{
+            shift: last_occurancies(&pattern[..m.saturating_sub(1)]).into_iter().map(|(c, idx)| (c, m-1-idx)).collect(),
+            pattern: pattern.to_vec()
+        }
+    }
+}
+
+impl<T> TextMatch<T> for Horspool<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let (mut s, m, n) = (0, self.pattern.len(), text.len());
+        Box::new(std::iter::from_fn(move || {
+            while m > 0 && s+m <= n {
+                let found = s;
+                s += *self.shift.get(&text[s+m-1]).unwrap_or(&m);
+                if str_match(found, text, self.pattern.as_slice()) {
+                    return Some(found);
+                }
+            }
+            None
+        }))
+    }
+}
+
+/// Sunday's Quick Search, the bad character rule on the symbol right after the window.
+pub struct Sunday<T>{
+    shift: HashMap<T, usize>,
+    pattern: Vec<T>
+}
+
+impl<T> Sunday<T> where T: Symbol {
+    pub fn new(pattern: &[T]) -> Self {
+        let m = pattern.len();
+        Self{
+            shift: last_occurancies(pattern).into_iter().map(|(c, idx)| (c, m-idx)).collect(),
+            pattern: pattern.to_vec()
+        }
+    }
+}
+
+impl<T> TextMatch<T> for Sunday<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let (mut s, m, n) = (0, self.pattern.len(), text.len());
+        Box::new(std::iter::from_fn(move || {
+            while m > 0 && s+m <= n {
+                let found = s;
+                s += text.get(s+m).map(|c| *self.shift.get(c).unwrap_or(&(m+1))).unwrap_or(m+1);
+                if str_match(found, text, self.pattern.as_slice()) {
+                    return Some(found);
+                }
+            }
+            None
+        }))
+    }
+}
+
+#[test]
+fn test_horspool_sunday() {
+    let text = "aaababaabaababaab".chars().collect::<Vec<char>>();
+    let pattern = "aabab".chars().collect::<Vec<char>>();
+    assert_eq!(vec![1, 9], Horspool::new(pattern.as_slice()).match_text(text.as_slice()));
+    assert_eq!(vec![1, 9], Sunday::new(pattern.as_slice()).match_text(text.as_slice()));
+    let text = b"TTATTATTATTATGAATTCTTATTAT";
+    let expected = naive_match(&text[..], b"TTATTAT");
+    assert_eq!(expected, Horspool::new(b"TTATTAT").match_text(text));
+    assert_eq!(expected, Sunday::new(b"TTATTAT").match_text(text));
+}
+
+/// Exact search with the "generic SIMD" prefilter: a block of windows is compared at
+/// once on its first and last symbol and only windows agreeing on both are checked with
+/// `str_match`. On byte texts SSE2 blocks are used on x86_64 and eight windows per `u64`
+/// (SWAR) everywhere else, other symbols are filtered one window at a time.
+pub struct ByteFinder<T>{
+    pattern: Vec<T>
+}
+
+impl<T> ByteFinder<T> where T: DenseSymbol {
+    pub fn new(pattern: &[T]) -> Self {
+        Self{
+            pattern: pattern.to_vec()
+        }
+    }
+}
+
+/// Bit 8k+7 is set iff byte k of the windows at `start` agrees with `first` and `last`.
+#[cfg_attr(all(target_arch = "x86_64", target_feature = "sse2"), allow(dead_code))]
+fn swar_candidates(text: &[u8], start: usize, last_idx: usize, first: u8, last: u8) -> u64 {
+    const LOW: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+    let load = |at: usize| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&text[at..at+8]);
+        u64::from_le_bytes(bytes)
+    };
+    let diff = (load(start) ^ u64::from_ne_bytes([first; 8])) | (load(start+last_idx) ^ u64::from_ne_bytes([last; 8]));
+    // High bit of every byte that is nonzero, exact since no carry crosses a byte
+    !(((diff & LOW) + LOW) | diff) & !LOW
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+mod block {
+    use std::arch::x86_64::{_mm_and_si128, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8};
+
+    pub const LANES: usize = 16;
+    pub const LANE_BITS: u32 = 1;
+
+    pub fn candidates(text: &[u8], start: usize, last_idx: usize, first: u8, last: u8) -> u64 {
+        assert!(start+last_idx+LANES <= text.len());
+        // SAFETY: SSE2 is part of the target and both unaligned loads are in bounds
+        unsafe {
+            let a = _mm_loadu_si128(text.as_ptr().add(start).cast());
+            let b = _mm_loadu_si128(text.as_ptr().add(start+last_idx).cast());
+            let eq = _mm_and_si128(_mm_cmpeq_epi8(a, _mm_set1_epi8(first as i8)), _mm_cmpeq_epi8(b, _mm_set1_epi8(last as i8)));
+            _mm_movemask_epi8(eq) as u32 as u64
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+mod block {
+    pub const LANES: usize = 8;
+    pub const LANE_BITS: u32 = 8;
+    pub use super::swar_candidates as candidates;
+}
+
+fn find_bytes<'a>(pattern: &'a [u8], text: &'a [u8]) -> Box<dyn Iterator<Item = usize> + 'a> {
+    let (m, n) = (pattern.len(), text.len());
+    let (first, last) = match (pattern.first(), pattern.last()) {
+        (Some(first), Some(last)) if m <= n => (*first, *last),
+        _ => return Box::new(std::iter::empty())
+    };
+    // Windows [base, s) are already in `mask`
+    let (mut s, mut base, mut mask) = (0, 0, 0u64);
+    Box::new(std::iter::from_fn(move || {
+        loop {
+            while mask != 0 {
+                let found = base + (mask.trailing_zeros() / block::LANE_BITS) as usize;
+                mask &= mask - 1;
+                if str_match(found, text, pattern) {
+                    return Some(found);
+                }
+            }
+            if s+m-1+block::LANES <= n {
+                base = s;
+                mask = block::candidates(text, s, m-1, first, last);
+                s += block::LANES;
+            } else if s+m <= n {
+                // Tail shorter than a block
+                base = s;
+                mask = (text[s] == first && text[s+m-1] == last) as u64;
+                s += 1;
+            } else {
+                return None;
+            }
+        }
+    }))
+}
+
+impl<T> TextMatch<T> for ByteFinder<T> where T: DenseSymbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        if let (Some(pattern), Some(text)) = (T::as_bytes(self.pattern.as_slice()), T::as_bytes(text)) {
+            return find_bytes(pattern, text);
+        }
+        let m = self.pattern.len();
+        let (first, last) = match (self.pattern.first(), self.pattern.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Box::new(std::iter::empty())
+        };
+        Box::new((0..(text.len()+1).saturating_sub(m)).filter(move |s| {
+            text[*s] == first && text[s+m-1] == last && str_match(*s, text, self.pattern.as_slice())
+        }))
+    }
+}
+
+#[test]
+fn test_byte_finder() {
+    let text = random_text(3, 3000, b"ACGT");
+    for pattern in [&b"A"[..], b"GT", b"ACG", b"TTAGC", b"CGTACGTACGTACGTACG", &text[1000..1100], &text[2990..]] {
+        let expected = naive_match(text.as_slice(), pattern);
+        assert_eq!(expected, ByteFinder::new(pattern).match_text(text.as_slice()));
+        // The portable prefilter agrees with a bytewise comparison
+        let (m, first, last) = (pattern.len(), pattern[0], pattern[pattern.len()-1]);
+        for s in 0..text.len()+1-m-8 {
+            let scalar = (0..8).filter(|k| text[s+k] == first && text[s+k+m-1] == last).fold(0, |mask, k| mask | 0x80 << (8*k));
+            assert_eq!(scalar, swar_candidates(text.as_slice(), s, m-1, first, last));
+        }
+    }
+    assert!(ByteFinder::new(&b""[..]).match_text(b"ACGT").is_empty());
+    assert!(ByteFinder::new(b"ACGTA").match_text(b"ACGT").is_empty());
+    assert_eq!(vec![0, 1, 2], ByteFinder::new(b"AA").match_text(b"AAAA"));
+    let chars = "ACGTACGA".chars().collect::<Vec<char>>();
+    assert_eq!(vec![0, 4], ByteFinder::new(&['A', 'C', 'G']).match_text(chars.as_slice()));
+}
+
+/// Baeza-Yates–Gonnet Shift-Or matcher. Bit `i` of the state is 0 while the last i+1 symbols
+/// match the first i+1 pattern positions, patterns longer than 64 symbols span several words.
+pub struct ShiftOr<T>{
+    /// Masks of the symbols with codes below [`DENSE`], `words` per code. Bit `i` is 0 if pattern position `i` accepts the symbol
+    table: Vec<u64>,
+    /// Masks of the symbols with larger codes, these are rare in most texts
+    masks: HashMap<T, Vec<u64>>,
+    words: usize,
+    m: usize
+}
+
+impl<T> ShiftOr<T> where T: DenseSymbol {
+    pub fn new(pattern: &[T]) -> Self {
+        let symbols: IndexSet<T> = pattern.iter().copied().collect();
+        Self::from_classes(pattern.len(), symbols, |i, c| pattern[i] == c)
+    }
+
+    /// Pattern of `m` positions where position `i` accepts the symbol `c` if `accepts(i, c)`,
+    /// symbols not in `symbols` are accepted nowhere.
+    pub fn from_classes<I, F>(m: usize, symbols: I, accepts: F) -> Self where I: IntoIterator<Item = T>, F: Fn(usize, T) -> bool {
+        let words = m.div_ceil(64);
+        let mut table = vec![!0u64; DENSE*words];
+        let mut masks = HashMap::new();
+        for c in symbols {
+            let mut mask = vec![!0u64; words];
+            for i in (0..m).filter(|i| accepts(*i, c)) {
+                mask[i / 64] &= !(1 << (i % 64));
+            }
+            match c.code() {
+                code if code < DENSE => table[code*words..(code+1)*words].copy_from_slice(&mask),
+                _ => { masks.insert(c, mask); }
+            }
+        }
+        Self{
+            table,
+            masks,
+            words,
+            m
+        }
+    }
+
+    /// Mask of `c`, `None` if no position accepts it.
+    fn mask(&self, c: T) -> Option<&[u64]> {
+        match c.code() {
+            code if code < DENSE => Some(&self.table[code*self.words..(code+1)*self.words]),
+            _ => self.masks.get(&c).map(|mask| mask.as_slice())
+        }
+    }
+}
+
+impl<T> TextMatch<T> for ShiftOr<T> where T: DenseSymbol {
+    fn pattern_len(&self) -> usize {
+        self.m
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let m = self.m;
+        if m == 0 {
+            return Box::new(std::iter::empty());
+        }
+        let (last, accept) = ((m-1) / 64, 1 << ((m-1) % 64));
+        if self.words == 1 {
+            // The whole state fits a single word
+            let mut state = !0u64;
+            return Box::new(text.iter().enumerate().filter_map(move |(idx, char)| {
+                let mask = match char.code() {
+                    code if code < DENSE => self.table[code],
+                    _ => self.masks.get(char).map_or(!0, |mask| mask[0])
+                };
+                state = (state << 1) | mask;
+                if state & accept == 0 {
+                    Some(idx+1-m)
+                } else {
+                    None
+                }
+            }));
+        }
+        let mut state = vec![!0u64; self.words];
+        let none = vec![!0u64; self.words];
+        Box::new(text.iter().enumerate().filter_map(move |(idx, char)| {
+            let mask = self.mask(*char).unwrap_or(none.as_slice());
+            let mut carry = 0;
+            for (word, mask) in state.iter_mut().zip(mask) {
+                let next = *word >> 63;
+                *word = (*word << 1) | carry | mask;
+                carry = next;
+            }
+            if state[last] & accept == 0 {
+                Some(idx+1-m)
+            } else {
+                None
+            }
+        }))
+    }
+}
+
+#[test]
+fn test_shift_or() {
+    let text = "aaababaabaababaab".chars().collect::<Vec<char>>();
+    let pattern = "aabab".chars().collect::<Vec<char>>();
+    assert_eq!(vec![1, 9], ShiftOr::new(pattern.as_slice()).match_text(text.as_slice()));
+    // Multiple words
+    let pattern = [b"ACGT".repeat(40), b"TTATTAT".to_vec()].concat();
+    let text = [b"GA".to_vec(), pattern.clone(), b"CG".to_vec(), pattern.clone(), pattern.clone()].concat();
+    assert_eq!(naive_match(&text, &pattern), ShiftOr::new(pattern.as_slice()).match_text(text.as_slice()));
+    // Symbols beyond the dense table
+    let text = "αβγ aβγ αβγαβγ".chars().collect::<Vec<char>>();
+    assert_eq!(vec![0, 8, 11], ShiftOr::new(&['α', 'β', 'γ']).match_text(text.as_slice()));
+    assert_eq!(vec![4], ShiftOr::new(&['a', 'β']).match_text(text.as_slice()));
+    assert!(ShiftOr::new(&b""[..]).match_text(b"ACGT").is_empty());
+}
+
+/// Aho-Corasick automaton, finds all patterns of a set in a single pass over the text.
+pub struct AhoCorasick<T>{
+    pub(crate) goto: HashMap<(usize, T), usize>,
+    pub(crate) fail: Vec<usize>,
+    /// Ids of the patterns ending in a state, including those reached by following `fail`
+    pub(crate) output: Vec<Vec<usize>>,
+    pub(crate) lengths: Vec<usize>
+}
+
+impl<T> AhoCorasick<T> where T: Symbol {
+    pub fn new<P>(patterns: &[P]) -> Self where P: AsRef<[T]> {
+        let mut goto = HashMap::new();
+        let mut children: Vec<Vec<(T, usize)>> = vec![vec![]];
+        let mut output: Vec<Vec<usize>> = vec![vec![]];
+        // Trie of all patterns, an empty one matches nowhere like with every other matcher
+        for (id, pattern) in patterns.iter().enumerate().filter(|(_, p)| !p.as_ref().is_empty()) {
+            let mut state = 0;
+            for char in pattern.as_ref() {
+                state = match goto.get(&(state, *char)) {
+                    Some(next) => *next,
+                    None => {
+                        let next = output.len();
+                        goto.insert((state, *char), next);
+                        children[state].push((*char, next));
+                        children.push(vec![]);
+                        output.push(vec![]);
+                        next
+                    }
+                };
+            }
+            output[state].push(id);
+        }
+        // Failure links in breadth first order, so fail[state] is known before its children
+        let mut fail = vec![0; output.len()];
+        let mut queue: VecDeque<usize> = children[0].iter().map(|(_, next)| *next).collect();
+        while let Some(state) = queue.pop_front() {
+            for (char, next) in &children[state] {
+                let mut f = fail[state];
+                while f != 0 && !goto.contains_key(&(f, *char)) {
+                    f = fail[f];
+                }
+                fail[*next] = *goto.get(&(f, *char)).unwrap_or(&0);
+                let inherited = output[fail[*next]].clone();
+                output[*next].extend(inherited);
+                queue.push_back(*next);
+            }
+        }
+        Self{
+            goto,
+            fail,
+            output,
+            lengths: patterns.iter().map(|p| p.as_ref().len()).collect()
+        }
+    }
+
+    fn step(&self, mut state: usize, symbol: T) -> usize {
+        loop {
+            if let Some(next) = self.goto.get(&(state, symbol)) {
+                return *next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state];
+        }
+    }
+
+}
+
+impl<T> MultiMatch<T> for AhoCorasick<T> where T: Symbol {
+    fn pattern_lens(&self) -> Vec<usize> {
+        self.lengths.clone()
+    }
+
+    fn match_patterns(&self, text: &[T]) -> Vec<(usize, usize)> {
+        let mut matches = vec![];
+        let mut state = 0;
+        for (idx, char) in text.iter().enumerate() {
+            state = self.step(state, *char);
+            for id in &self.output[state] {
+                matches.push((*id, idx+1-self.lengths[*id]));
+            }
+        }
+        matches.sort_unstable_by_key(|(id, pos)| (*pos, *id));
+        matches
+    }
+}
+
+#[test]
+fn test_aho_corasick() {
+    let patterns = ["he", "she", "his", "hers"].iter().map(|p| p.chars().collect()).collect::<Vec<Vec<char>>>();
+    let ac = AhoCorasick::new(patterns.as_slice());
+    let text = "ushers".chars().collect::<Vec<char>>();
+    assert_eq!(vec![(1, 1), (0, 2), (3, 2)], ac.match_patterns(text.as_slice()));
+}
+
+#[test]
+fn test_aho_corasick_naive() {
+    let text = b"GAATTCGGATCCATTTAAATGAATTCAATTGGATCCC";
+    let patterns: [&[u8]; 6] = [b"GAATTC", b"", b"GGATCC", b"ATTTAAAT", b"AATT", b"C"];
+    let ac = AhoCorasick::new(&patterns);
+    let mut expected = vec![];
+    for (id, pattern) in patterns.iter().enumerate().filter(|(_, p)| !p.is_empty()) {
+        expected.extend(naive_match(&text[..], pattern).into_iter().map(|pos| (id, pos)));
+    }
+    expected.sort_unstable_by_key(|(id, pos)| (*pos, *id));
+    assert_eq!(expected, ac.match_patterns(text));
+    let patterns: [&[u8]; 2] = [b"", b"AC"];
+    assert_eq!(vec![(1, 0)], AhoCorasick::new(&patterns).match_patterns(b"ACG"));
+    assert_eq!(vec![(1, 0)], MultiRabinKarp::new(&patterns).match_patterns(b"ACG"));
+}
+
+#[test]
+fn test_boyer_moore_shifts() {
+    // The first pattern symbol has to match as well, and neither rule may skip an occurrence
+    assert_eq!(vec![0, 1, 2, 3, 6, 7, 8], BoyerMoore::new(b"AA").match_text(b"AAAAATAAAA"));
+    assert_eq!(vec![2], BoyerMoore::new(b"ABABA").match_text(b"AAABABA"));
+    assert_eq!(vec![0, 1], BoyerMoore::new(b"A").match_text(b"AAB"));
+    assert!(BoyerMoore::new(b"AB").match_text(b"A").is_empty());
+    assert!(BoyerMoore::new(&b""[..]).match_text(b"A").is_empty());
+}
+
+#[test]
+fn test_find_matches() {
+    let text = b"GAATTCGGATCCATTTAAATGAATTC";
+    assert_eq!(vec![Match::new(0, 6), Match::new(20, 26)], BoyerMoore::new(b"GAATTC").find_matches(text));
+    let patterns: [&[u8]; 2] = [b"GGATCC", b"ATTTAAAT"];
+    let expected = vec![Match::new(6, 12), Match::new(12, 20).with_pattern(1)];
+    assert_eq!(expected, AhoCorasick::new(&patterns).find_matches(text));
+    assert_eq!(expected, MultiRabinKarp::new(&patterns).find_matches(text));
+}
+
+#[test]
+fn test_match_semantics() {
+    let text = "AAAAATAAAA";
+    let native = text.match_indices("AA").map(|(idx, _)| idx).collect::<Vec<usize>>();
+    assert_eq!(vec![0, 2, 6, 8], native);
+    let matchers: Vec<Box<dyn TextMatch<u8>>> = vec![Box::new(KnuthMorrisPratt::new(b"AA")), Box::new(BoyerMoore::new(b"AA")), Box::new(ShiftOr::new(b"AA"))];
+    for matcher in matchers {
+        assert_eq!(vec![0, 1, 2, 3, 6, 7, 8], matcher.match_text_with(text.as_bytes(), MatchSemantics::Overlapping));
+        assert_eq!(native, matcher.match_text_with(text.as_bytes(), MatchSemantics::NonOverlapping));
+        assert_eq!(native, matcher.match_text_with(text.as_bytes(), MatchSemantics::LeftmostLongest));
+    }
+    let patterns: [&[u8]; 3] = [b"ab", b"abcd", b"cd"];
+    let text = b"abcdab";
+    for matches in [AhoCorasick::new(&patterns).match_patterns_with(text, MatchSemantics::NonOverlapping), MultiRabinKarp::new(&patterns).match_patterns_with(text, MatchSemantics::NonOverlapping)] {
+        assert_eq!(vec![(0, 0), (2, 2), (0, 4)], matches);
+    }
+    for matches in [AhoCorasick::new(&patterns).match_patterns_with(text, MatchSemantics::LeftmostLongest), MultiRabinKarp::new(&patterns).match_patterns_with(text, MatchSemantics::LeftmostLongest)] {
+        assert_eq!(vec![(1, 0), (0, 4)], matches);
+    }
+    // Streams carry the selection over chunk borders and start over on reset
+    let (kmp, bm) = (KnuthMorrisPratt::new(b"AA"), BoyerMoore::new(b"AA"));
+    let streams: Vec<Box<dyn StreamMatch<u8>>> = vec![
+        Box::new(kmp.stream().with_semantics(MatchSemantics::NonOverlapping)),
+        Box::new(bm.stream().with_semantics(MatchSemantics::NonOverlapping)),
+        Box::new(RealTimeKmp::new(b"AA").with_semantics(MatchSemantics::NonOverlapping))
+    ];
+    for mut stream in streams {
+        for _ in 0..2 {
+            assert_eq!(native, match_reader(&b"AAAAATAAAA"[..], &mut *stream, 3).unwrap());
+            stream.reset();
+        }
+    }
+}
+
+#[test]
+fn test_bytes() {
+    let text = b"TTATTATGAATTCTTATTAT";
+    let pattern = b"TTATTAT";
+    assert_eq!(vec![0, 13], naive_match(&text[..], &pattern[..]));
+    assert_eq!(vec![0, 13], Automat::new(pattern).match_text(text));
+    assert_eq!(vec![0, 13], KnuthMorrisPratt::new(pattern).match_text(text));
+    assert_eq!(vec![0, 13], BoyerMoore::new(pattern).match_text(text));
+    assert_eq!(vec![0, 13], RabinKarp::new(pattern, b"ACGT").match_text(text));
+}
+
+#[test]
+fn test_stream() {
+    let text = "aaababaabaababaab".chars().collect::<Vec<char>>();
+    let pattern = "aabab".chars().collect::<Vec<char>>();
+    let automat = Automat::new(pattern.as_slice());
+    let kmp = KnuthMorrisPratt::new(pattern.as_slice());
+    let bm = BoyerMoore::new(pattern.as_slice());
+    let streams: Vec<Box<dyn StreamMatch<char>>> = vec![Box::new(automat.stream()), Box::new(kmp.stream()), Box::new(bm.stream())];
+    for mut stream in streams {
+        for size in 1..text.len() {
+            let result: Vec<usize> = text.chunks(size).flat_map(|chunk| stream.feed(chunk)).collect();
+            assert_eq!(vec![1, 9], result);
+            assert_eq!(text.len(), stream.offset());
+            stream.reset();
+        }
+    }
+}
+
+#[test]
+fn test_match_reader() {
+    let text = b"TTATTATGAATTCTTATTAT";
+    let automat = Automat::new(b"TTATTAT");
+    let mut stream = automat.stream();
+    assert_eq!(vec![0, 13], match_reader(&text[..], &mut stream, 3).unwrap());
+}
+
+#[test]
+fn test_match_fasta() {
+    let fasta = b">TTATTAT header\nGGTTAT\nTATCC\r\n>second\nTTATTATTTATTAT\n";
+    let bm = BoyerMoore::new(b"TTATTAT");
+    let automat = Automat::new(b"TTATTAT");
+    let streams: Vec<Box<dyn StreamMatch<u8>>> = vec![Box::new(bm.stream()), Box::new(automat.stream())];
+    for mut stream in streams {
+        let matches = vec![Match::new(2, 9), Match::new(0, 7).with_record(1), Match::new(7, 14).with_record(1)];
+        assert_eq!(matches, match_fasta(&fasta[..], &mut *stream).unwrap());
+    }
+    assert!(match_fasta(&b"TTATTAT"[..], &mut bm.stream()).is_err());
+}
+
+#[test]
+fn test_find_iter() {
+    let text = "aaababaabaababaab".chars().collect::<Vec<char>>();
+    let pattern = "aabab".chars().collect::<Vec<char>>();
+    let matchers: Vec<Box<dyn TextMatch<char>>> = vec![
+        Box::new(Automat::new(pattern.as_slice())),
+        Box::new(KnuthMorrisPratt::new(pattern.as_slice())),
+        Box::new(BoyerMoore::new(pattern.as_slice())),
+        Box::new(RabinKarp::new(pattern.as_slice(), &['a', 'b']))
+    ];
+    for matcher in matchers {
+        assert_eq!(vec![1, 9], matcher.find_iter(text.as_slice()).collect::<Vec<usize>>());
+        assert_eq!(Some(1), matcher.find_first(text.as_slice()));
+        assert_eq!(2, matcher.count(text.as_slice()));
+        assert!(matcher.is_match(text.as_slice()));
+        assert!(!matcher.is_match(&text[..5]));
+    }
+}
+
+#[test]
+fn test_prefix_function_borders() {
+    let pattern = "aabaabaaaab".chars().collect::<Vec<char>>();
+    let pi = z_algorithm::prefix_function(pattern.as_slice());
+    for q in 1..=pattern.len() {
+        assert_eq!(Automat::sigma_suffix(&pattern[..q]), pi[q]);
+    }
+}
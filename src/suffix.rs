@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use crate::string_match::{Match, Symbol};
+
 #[derive(Debug)]
 enum SuffixNode{
     Edge{label: Vec<char>, childs: Vec<SuffixNode>},
@@ -91,6 +95,232 @@ fn test_wotd(){
     println!("{:#?}", st);
 }
 
+/// Entries of the LCP array per block of the range minimum structure.
+const RMQ_BLOCK: usize = 32;
+
+/// Suffix array with LCP array and a sparse table over its blocks, so the longest
+/// common extension of any two suffixes is answered in constant time.
+#[derive(Debug)]
+pub struct SuffixArray{
+    suffixes: Vec<usize>,
+    rank: Vec<usize>,
+    /// `lcp[r]` is the common prefix of the suffixes at rank r-1 and r
+    lcp: Vec<usize>,
+    /// `sparse[l][b]` is the minimum of the LCP entries in blocks b..b+2^l
+    sparse: Vec<Vec<usize>>
+}
+
+impl SuffixArray{
+    /// Induced sorting (SA-IS) and Kasai's LCP array, both in O(n).
+    pub fn new<T>(input: &[T]) -> Self where T: Symbol + Ord {
+        let n = input.len();
+        let mut sigma = input.to_vec();
+        sigma.sort_unstable();
+        sigma.dedup();
+        let ids: HashMap<T, usize> = sigma.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+        let codes: Vec<usize> = input.iter().map(|c| ids[c]).collect();
+        let suffixes = sa_is(codes.as_slice(), sigma.len().saturating_sub(1));
+        let mut rank = vec![0; n];
+        for (r, s) in suffixes.iter().enumerate() {
+            rank[*s] = r;
+        }
+        // Kasai, the common prefix shrinks by at most one from suffix i to suffix i+1
+        let mut lcp = vec![0; n];
+        let mut h = 0;
+        for i in 0..n {
+            if rank[i] > 0 {
+                let j = suffixes[rank[i]-1];
+                while i+h < n && j+h < n && codes[i+h] == codes[j+h] {
+                    h += 1;
+                }
+                lcp[rank[i]] = h;
+                h = h.saturating_sub(1);
+            } else {
+                h = 0;
+            }
+        }
+        let mut sparse = vec![lcp.chunks(RMQ_BLOCK).map(|b| *b.iter().min().unwrap()).collect::<Vec<usize>>()];
+        let blocks = sparse[0].len();
+        let mut width = 1;
+        while 2*width <= blocks {
+            let prev = &sparse[sparse.len()-1];
+            let level = (0..blocks-2*width+1).map(|b| prev[b].min(prev[b+width])).collect();
+            sparse.push(level);
+            width *= 2;
+        }
+        Self{
+            suffixes,
+            rank,
+            lcp,
+            sparse
+        }
+    }
+
+    /// Minimum of `lcp[lo..=hi]`, whole blocks from the sparse table and the partial ones scanned.
+    fn range_min(&self, lo: usize, hi: usize) -> usize {
+        let (first, last) = (lo/RMQ_BLOCK, hi/RMQ_BLOCK);
+        if first == last {
+            return *self.lcp[lo..=hi].iter().min().unwrap();
+        }
+        let head = *self.lcp[lo..(first+1)*RMQ_BLOCK].iter().min().unwrap();
+        let tail = *self.lcp[last*RMQ_BLOCK..=hi].iter().min().unwrap();
+        let mut min = head.min(tail);
+        if first+1 < last {
+            let (lo, hi) = (first+1, last-1);
+            let level = (usize::BITS - 1 - (hi-lo+1).leading_zeros()) as usize;
+            min = min.min(self.sparse[level][lo]).min(self.sparse[level][hi+1-(1 << level)]);
+        }
+        min
+    }
+
+    /// Starting positions of the suffixes in lexicographic order.
+    pub fn suffixes(&self) -> &[usize] {
+        &self.suffixes
+    }
+
+    /// Occurrences of `pattern` in the `text` the array was built from, sorted by position.
+    /// Binary search over the suffixes, O(m log n). Panics if `text` has a different length,
+    /// it can't be the one the array was built from.
+    pub fn find_matches<T>(&self, text: &[T], pattern: &[T]) -> Vec<Match> where T: Ord {
+        assert_eq!(self.suffixes.len(), text.len(), "text differs from the one the suffix array was built from");
+        let m = pattern.len();
+        if m == 0 {
+            return vec![];
+        }
+        let prefix = |s: usize| &text[s..(s+m).min(text.len())];
+        let lo = self.suffixes.partition_point(|s| prefix(*s) < pattern);
+        let hi = self.suffixes.partition_point(|s| prefix(*s) <= pattern);
+        let mut starts = self.suffixes[lo..hi].to_vec();
+        starts.sort_unstable();
+        starts.into_iter().map(|s| Match::new(s, s+m)).collect()
+    }
+
+    /// Longest common extension, the length of the common prefix of the suffixes starting at `i` and `j`.
+    pub fn lce(&self, i: usize, j: usize) -> usize {
+        let n = self.rank.len();
+        if i == j {
+            return n-i;
+        }
+        if i >= n || j >= n {
+            return 0;
+        }
+        self.range_min(self.rank[i].min(self.rank[j])+1, self.rank[i].max(self.rank[j]))
+    }
+}
+
+/// Suffix array of `s`, whose symbols are at most `upper`, by induced sorting in O(n).
+/// The suffixes starting with a leftmost S-type position (LMS) are sorted recursively
+/// and then induce the order of all other suffixes.
+fn sa_is(s: &[usize], upper: usize) -> Vec<usize> {
+    let n = s.len();
+    match n {
+        0 => return vec![],
+        1 => return vec![0],
+        2 => return if s[0] < s[1] { vec![0, 1] } else { vec![1, 0] },
+        _ => ()
+    }
+    const NONE: usize = usize::MAX;
+    // ls[i] is set if the suffix at i is S-type, smaller than the one at i+1
+    let mut ls = vec![false; n];
+    for i in (0..n-1).rev() {
+        ls[i] = if s[i] == s[i+1] { ls[i+1] } else { s[i] < s[i+1] };
+    }
+    // Bucket starts of the L-type suffixes (sum_l) and the S-type ones (sum_s) of every symbol
+    let mut sum_l = vec![0; upper+1];
+    let mut sum_s = vec![0; upper+1];
+    for i in 0..n {
+        if ls[i] {
+            sum_l[s[i]+1] += 1;
+        } else {
+            sum_s[s[i]] += 1;
+        }
+    }
+    for c in 0..=upper {
+        sum_s[c] += sum_l[c];
+        if c < upper {
+            sum_l[c+1] += sum_s[c];
+        }
+    }
+    let mut sa = vec![NONE; n];
+    let induce = |lms: &[usize], sa: &mut Vec<usize>| {
+        sa.iter_mut().for_each(|v| *v = NONE);
+        let mut buf = sum_s.clone();
+        for d in lms {
+            sa[buf[s[*d]]] = *d;
+            buf[s[*d]] += 1;
+        }
+        buf.copy_from_slice(&sum_l);
+        sa[buf[s[n-1]]] = n-1;
+        buf[s[n-1]] += 1;
+        for i in 0..n {
+            let v = sa[i];
+            if v != NONE && v >= 1 && !ls[v-1] {
+                sa[buf[s[v-1]]] = v-1;
+                buf[s[v-1]] += 1;
+            }
+        }
+        buf.copy_from_slice(&sum_l);
+        for i in (0..n).rev() {
+            let v = sa[i];
+            if v != NONE && v >= 1 && ls[v-1] {
+                buf[s[v-1]+1] -= 1;
+                sa[buf[s[v-1]+1]] = v-1;
+            }
+        }
+    };
+    let mut lms_map = vec![NONE; n+1];
+    let lms: Vec<usize> = (1..n).filter(|i| !ls[i-1] && ls[*i]).collect();
+    for (id, i) in lms.iter().enumerate() {
+        lms_map[*i] = id;
+    }
+    induce(lms.as_slice(), &mut sa);
+    let m = lms.len();
+    if m > 0 {
+        // Name the LMS substrings in sorted order, equal substrings share a name
+        let mut sorted_lms: Vec<usize> = sa.iter().copied().filter(|v| lms_map[*v] != NONE).collect();
+        let mut rec_s = vec![0; m];
+        let mut rec_upper = 0;
+        for i in 1..m {
+            let (mut l, mut r) = (sorted_lms[i-1], sorted_lms[i]);
+            let end_l = lms.get(lms_map[l]+1).copied().unwrap_or(n);
+            let end_r = lms.get(lms_map[r]+1).copied().unwrap_or(n);
+            let mut same = end_l-l == end_r-r;
+            if same {
+                while l < end_l && s[l] == s[r] {
+                    l += 1;
+                    r += 1;
+                }
+                same = l < n && s[l] == s[r];
+            }
+            if !same {
+                rec_upper += 1;
+            }
+            rec_s[lms_map[sorted_lms[i]]] = rec_upper;
+        }
+        let rec_sa = sa_is(rec_s.as_slice(), rec_upper);
+        for i in 0..m {
+            sorted_lms[i] = lms[rec_sa[i]];
+        }
+        induce(sorted_lms.as_slice(), &mut sa);
+    }
+    sa
+}
+
+#[test]
+fn test_suffix_array() {
+    let sa = SuffixArray::new(&['b','a','n','a','n','a']);
+    assert_eq!(&[5, 3, 1, 0, 4, 2], sa.suffixes());
+    assert_eq!(3, sa.lce(1, 3));
+    assert_eq!(0, sa.lce(0, 1));
+    assert_eq!(2, sa.lce(2, 4));
+    assert_eq!(1, sa.lce(5, 1));
+    let text = b"GAATTCGGATCCATTTAAATGAATTC";
+    let sa = SuffixArray::new(text);
+    assert_eq!(vec![Match::new(0, 6), Match::new(20, 26)], sa.find_matches(text, b"GAATTC"));
+    assert_eq!(vec![Match::new(11, 12), Match::new(25, 26)], sa.find_matches(text, b"C").into_iter().filter(|m| m.start > 10).collect::<Vec<Match>>());
+    assert!(sa.find_matches(text, b"GAATTCG").len() == 1 && sa.find_matches(text, b"TTT").len() == 1 && sa.find_matches(text, b"CCC").is_empty());
+}
+
 fn implicit(){}
 
 fn ukkonen(){}
@@ -103,4 +333,11 @@ fn mum(input: &[char], len: usize) {
 /// Minimal Unique Substring
 fn mus(input: &[char], len: usize) {
 
-}
\ No newline at end of file
+}
+
+#[test]
+#[should_panic]
+fn test_suffix_array_other_text() {
+    let sa = SuffixArray::new(b"GAATTC");
+    sa.find_matches(b"GAATTCGAATTC", b"GAATTC");
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::string_match::{Symbol, TextMatch};
+
+/// Prime 119·2²³+1, its multiplicative group has 3 as generator and roots of unity
+/// for every power of two up to [`MAX_SIZE`].
+const MODULUS: u64 = 998_244_353;
+const ROOT: u64 = 3;
+const MAX_SIZE: usize = 1 << 23;
+
+fn pow_mod(mut base: u64, mut exp: u64) -> u64 {
+    let mut result = 1;
+    base %= MODULUS;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % MODULUS;
+        }
+        base = base * base % MODULUS;
+        exp >>= 1;
+    }
+    result
+}
+
+/// Iterative radix-2 number theoretic transform in place, an FFT over the integers modulo
+/// [`MODULUS`], so unlike a floating point one it is exact. `a.len()` has to be a power of two.
+fn ntt(a: &mut [u64], invert: bool) {
+    let n = a.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j ^= bit;
+        if i < j {
+            a.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let w_len = pow_mod(ROOT, (MODULUS-1) / len as u64);
+        let w_len = if invert { pow_mod(w_len, MODULUS-2) } else { w_len };
+        for start in (0..n).step_by(len) {
+            let mut w = 1;
+            for k in 0..len/2 {
+                let u = a[start+k];
+                let v = a[start+k+len/2] * w % MODULUS;
+                a[start+k] = (u + v) % MODULUS;
+                a[start+k+len/2] = (u + MODULUS - v) % MODULUS;
+                w = w * w_len % MODULUS;
+            }
+        }
+        len <<= 1;
+    }
+    if invert {
+        let n_inv = pow_mod(n as u64, MODULUS-2);
+        for x in a.iter_mut() {
+            *x = *x * n_inv % MODULUS;
+        }
+    }
+}
+
+/// Exact matching with don't-care symbols, the wildcard matches any single symbol in the
+/// pattern and, if enabled, in the text as well. No regex semantics, every position is one symbol.
+///
+/// With symbols coded as 1..=σ and wildcards as 0, a window at `i` matches iff
+/// Σ p_j t_{i+j} (p_j - t_{i+j})² = 0. The three correlations behind it are computed
+/// with number theoretic transforms over blocks of about 2m symbols, O(n log m) overall.
+/// The sum is exact modulo a prime, so no match is missed however large the alphabet,
+/// and every candidate is verified since a nonzero sum may vanish modulo the prime.
+pub struct Wildcard<T>{
+    pattern: Vec<T>,
+    wildcard: T,
+    in_text: bool
+}
+
+impl<T> Wildcard<T> where T: Symbol {
+    pub fn new(pattern: &[T], wildcard: T, in_text: bool) -> Self {
+        Self{
+            pattern: pattern.to_vec(),
+            wildcard,
+            in_text
+        }
+    }
+
+    fn symbol_match(&self, p: T, t: T) -> bool {
+        p == self.wildcard || (self.in_text && t == self.wildcard) || p == t
+    }
+
+    fn window_match(&self, text: &[T], start: usize) -> bool {
+        self.pattern.iter().zip(&text[start..]).all(|(p, t)| self.symbol_match(*p, *t))
+    }
+
+    /// Straightforward O(nm) search, the reference for the FFT based one.
+    pub fn naive_match(&self, text: &[T]) -> Vec<usize> {
+        let m = self.pattern.len();
+        (0..(text.len()+1).saturating_sub(m)).filter(|s| self.window_match(text, *s)).collect()
+    }
+
+    /// Positions of all matches computed with FFTs, number theoretic ones.
+    pub fn fft_match(&self, text: &[T]) -> Vec<usize> {
+        self.fft_iter(text).collect()
+    }
+
+    /// Lazily yield the matches computed with FFTs, one block of text at a time, so the search
+    /// can stop after the first block with a hit. Patterns too long for a transform of at most
+    /// [`MAX_SIZE`] points are matched naively.
+    pub fn fft_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let (m, n) = (self.pattern.len(), text.len());
+        if m == 0 || m > n {
+            return Box::new(std::iter::empty());
+        }
+        let size = (2*m).next_power_of_two();
+        if size > MAX_SIZE {
+            return Box::new((0..=n-m).filter(move |s| self.window_match(text, *s)));
+        }
+        // Symbols missing from the pattern all share one code, they never equal a pattern symbol
+        let mut codes: HashMap<T, u64> = HashMap::new();
+        for c in self.pattern.iter().filter(|c| **c != self.wildcard) {
+            let next = codes.len() as u64 + 1;
+            codes.entry(*c).or_insert(next);
+        }
+        let other = codes.len() as u64 + 1;
+        let code = move |c: T, wild: bool| {
+            if wild && c == self.wildcard { 0 } else { *codes.get(&c).unwrap_or(&other) }
+        };
+        let powers = |x: u64| [x, x * x % MODULUS, x * x % MODULUS * x % MODULUS];
+
+        // Reversed pattern, so the convolution with the text becomes a correlation
+        let mut p = [vec![0; size], vec![0; size], vec![0; size]];
+        for (j, c) in self.pattern.iter().rev().enumerate() {
+            for (k, x) in powers(code(*c, true)).iter().enumerate() {
+                p[k][j] = *x;
+            }
+        }
+        for f in p.iter_mut() {
+            ntt(f, false);
+        }
+
+        let step = size-m+1;
+        Box::new((0..=n-m).step_by(step).flat_map(move |start| {
+            let block = &text[start..(start+size).min(n)];
+            let mut t = [vec![0; size], vec![0; size], vec![0; size]];
+            for (j, c) in block.iter().enumerate() {
+                for (k, x) in powers(code(*c, self.in_text)).iter().enumerate() {
+                    t[k][j] = *x;
+                }
+            }
+            for f in t.iter_mut() {
+                ntt(f, false);
+            }
+            // p³·t - 2p²·t² + p·t³
+            let mut sum: Vec<u64> = (0..size).map(|i| {
+                let square = p[1][i] * t[1][i] % MODULUS;
+                (p[2][i] * t[0][i] % MODULUS + p[0][i] * t[2][i] % MODULUS + 2 * (MODULUS - square)) % MODULUS
+            }).collect();
+            ntt(&mut sum, true);
+            (0..step.min(block.len()+1-m))
+                .filter(|a| sum[a+m-1] == 0 && self.window_match(text, start+a))
+                .map(|a| start+a)
+                .collect::<Vec<usize>>()
+        }))
+    }
+}
+
+impl<T> TextMatch<T> for Wildcard<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        self.fft_iter(text)
+    }
+}
+
+#[test]
+fn test_wildcard() {
+    let text = "ACGTTGACGATTGACCAGTAGGACATTGAC".chars().collect::<Vec<char>>();
+    let pattern = "TG?C".chars().collect::<Vec<char>>();
+    let wildcard = Wildcard::new(pattern.as_slice(), '?', false);
+    assert_eq!(vec![4, 11, 26], wildcard.naive_match(text.as_slice()));
+    assert_eq!(vec![4, 11, 26], wildcard.match_text(text.as_slice()));
+}
+
+#[test]
+fn test_wildcard_in_text() {
+    // Deterministic pseudo random DNA with masked regions, long enough for several FFT blocks
+    let text = crate::string_match::random_text(17, 2000, b"ACGTN");
+    for pattern in [&b"ANG"[..], b"NNACN", b"TTNANNGN", b"A"] {
+        for in_text in [false, true] {
+            let wildcard = Wildcard::new(pattern, b'N', in_text);
+            assert_eq!(wildcard.naive_match(text.as_slice()), wildcard.fft_match(text.as_slice()));
+        }
+    }
+}
+
+#[test]
+fn test_wildcard_large_alphabet() {
+    // Cubes of codes in the tens of thousands exceed what f64 sums represent exactly
+    for sigma in [500u32, 5000, 60000] {
+        let alphabet: Vec<u32> = (1..=sigma).collect();
+        let text = crate::string_match::random_text(5, 20000, alphabet.as_slice());
+        let mut pattern = text[10000..12000].to_vec();
+        for c in pattern.iter_mut().step_by(7) {
+            *c = 0;
+        }
+        let wildcard = Wildcard::new(pattern.as_slice(), 0, false);
+        assert_eq!(vec![10000], wildcard.fft_match(text.as_slice()));
+        assert_eq!(Some(10000), wildcard.find_first(text.as_slice()));
+        assert_eq!(wildcard.naive_match(text.as_slice()), wildcard.fft_match(text.as_slice()));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::string_match::{Symbol, TextMatch};
+
+/// Gusfield's fundamental preprocessing, `z[i]` is the length of the longest substring
+/// starting at `i` that is also a prefix of `s`. By convention `z[0]` is the whole length.
+pub fn z_array<T>(s: &[T]) -> Vec<usize> where T: Eq {
+    let n = s.len();
+    let mut z = vec![0; n];
+    if n == 0 {
+        return z;
+    }
+    z[0] = n;
+    // [l, r) is the Z-box reaching furthest to the right
+    let (mut l, mut r) = (0, 0);
+    for i in 1..n {
+        let mut k = if i < r { z[i-l].min(r-i) } else { 0 };
+        while i+k < n && s[k] == s[i+k] {
+            k += 1;
+        }
+        z[i] = k;
+        if i+k > r {
+            l = i;
+            r = i+k;
+        }
+    }
+    z
+}
+
+/// `n[j]` is the length of the longest suffix of `s[..=j]` that is also a suffix of `s`,
+/// the Z-array of the reversed string read backwards. The good suffix rule is built on it.
+pub fn n_array<T>(s: &[T]) -> Vec<usize> where T: Copy + Eq {
+    let reversed: Vec<T> = s.iter().rev().copied().collect();
+    let mut n = z_array(reversed.as_slice());
+    n.reverse();
+    n
+}
+
+/// `pi[q]` is the length of the longest proper prefix of `pattern[..q]` that is also its suffix,
+/// for `q` in `0..=m`. Every Z-box `[i, i+z[i])` is such a border of the prefix ending with it.
+pub fn prefix_function<T>(pattern: &[T]) -> Vec<usize> where T: Eq {
+    let m = pattern.len();
+    let z = z_array(pattern);
+    let mut pi = vec![0; m+1];
+    for (i, len) in z.iter().enumerate().skip(1) {
+        pi[i+len] = pi[i+len].max(*len);
+    }
+    // A border of pattern[..q+1] shortened by one is a border of pattern[..q]
+    for q in (1..m).rev() {
+        pi[q] = pi[q].max(pi[q+1].saturating_sub(1));
+    }
+    pi
+}
+
+/// Exact matcher on the Z-array of the pattern, the Z-values against the text
+/// are computed on the fly by reusing the rightmost Z-box in the text.
+pub struct ZMatcher<T>{
+    pattern: Vec<T>,
+    z: Vec<usize>
+}
+
+impl<T> ZMatcher<T> where T: Symbol {
+    pub fn new(pattern: &[T]) -> Self {
+        Self{
+            pattern: pattern.to_vec(),
+            z: z_array(pattern)
+        }
+    }
+}
+
+impl<T> TextMatch<T> for ZMatcher<T> where T: Symbol {
+    fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    fn find_iter<'a>(&'a self, text: &'a [T]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let (m, n) = (self.pattern.len(), text.len());
+        // text[l..r] equals pattern[..r-l]
+        let (mut l, mut r) = (0, 0);
+        Box::new((0..n).filter(move |i| {
+            let mut k = if *i < r { self.z[i-l].min(r-i) } else { 0 };
+            if k == r.saturating_sub(*i) {
+                while k < m && i+k < n && self.pattern[k] == text[i+k] {
+                    k += 1;
+                }
+                if i+k > r {
+                    l = *i;
+                    r = i+k;
+                }
+            }
+            m > 0 && k == m
+        }))
+    }
+}
+
+#[test]
+fn test_z_array() {
+    let s = "aabcaabxaaaz".chars().collect::<Vec<char>>();
+    assert_eq!(vec![12, 1, 0, 0, 3, 1, 0, 0, 2, 2, 1, 0], z_array(s.as_slice()));
+    assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 12], n_array(s.as_slice()));
+    assert_eq!(vec![0, 2, 0, 4], n_array(b"abab"));
+}
+
+#[test]
+fn test_prefix_function() {
+    let pattern = "ababaca".chars().collect::<Vec<char>>();
+    assert_eq!(vec![0, 0, 0, 1, 2, 3, 0, 1], prefix_function(pattern.as_slice()));
+    assert_eq!(vec![0, 0, 1, 2, 3], prefix_function(b"aaaa"));
+}
+
+#[test]
+fn test_z_matcher() {
+    let text = "aaababaabaababaab".chars().collect::<Vec<char>>();
+    let pattern = "aabab".chars().collect::<Vec<char>>();
+    assert_eq!(vec![1, 9], ZMatcher::new(pattern.as_slice()).match_text(text.as_slice()));
+    assert_eq!(vec![0, 1, 2], ZMatcher::new(b"aa").match_text(b"aaaa"));
+}